@@ -0,0 +1,202 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use std::fmt;
+
+// Flight level used for unlimited upper limits
+const UNLIMITED_LEVEL: u32 = 999;
+
+// Feet to metres
+const FEET_TO_METRES: f64 = 0.3048;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    Feet,
+    FlightLevel,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reference {
+    Agl,
+    Amsl,
+    Standard,
+}
+
+// Vertical limit, e.g. FL195, 3000 ft or SFC
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Altitude {
+    pub value: u32,
+    pub unit: Unit,
+    pub reference: Reference,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AltError(pub String);
+
+impl fmt::Display for AltError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid altitude \"{}\"", self.0)
+    }
+}
+
+impl Altitude {
+    // Height in feet, flight levels taken as standard pressure altitude
+    pub fn feet(&self) -> u32 {
+        match self.unit {
+            Unit::Feet => self.value,
+            Unit::FlightLevel => self.value * 100,
+        }
+    }
+
+    // Height in metres
+    pub fn metres(&self) -> f64 {
+        self.feet() as f64 * FEET_TO_METRES
+    }
+
+    // Ground level
+    pub fn is_surface(&self) -> bool {
+        self.value == 0 && self.reference == Reference::Agl
+    }
+
+    // No upper limit
+    pub fn is_unlimited(&self) -> bool {
+        self.unit == Unit::FlightLevel && self.value == UNLIMITED_LEVEL
+    }
+
+    // Normalised flight level, altitudes rounded down
+    pub fn flight_level(&self) -> u16 {
+        (self.feet() / 100).min(u16::MAX as u32) as u16
+    }
+}
+
+// Parse source level, e.g. "FL195", "3000 ft", "2000 ft amsl", "SFC"
+// or "UNL". Altitudes without an explicit reference are AMSL
+pub fn parse_altitude(value: &str) -> Result<Altitude, AltError> {
+    let err = || AltError(value.to_string());
+    let level = value.trim().to_ascii_uppercase();
+
+    match level.as_str() {
+        "SFC" | "GND" => {
+            return Ok(Altitude {
+                value: 0,
+                unit: Unit::Feet,
+                reference: Reference::Agl,
+            })
+        }
+        "UNL" | "UNLTD" | "UNLIMITED" => {
+            return Ok(Altitude {
+                value: UNLIMITED_LEVEL,
+                unit: Unit::FlightLevel,
+                reference: Reference::Standard,
+            })
+        }
+        _ => (),
+    }
+
+    if let Some(fl) = level.strip_prefix("FL") {
+        let value = fl.trim().parse::<u32>().map_err(|_| err())?;
+        return Ok(Altitude {
+            value,
+            unit: Unit::FlightLevel,
+            reference: Reference::Standard,
+        });
+    }
+
+    let mut parts = level.split_whitespace();
+    let value = parts
+        .next()
+        .and_then(|x| x.parse::<u32>().ok())
+        .ok_or_else(err)?;
+    if parts.next() != Some("FT") {
+        return Err(err());
+    }
+    let reference = match parts.next() {
+        None | Some("AMSL") | Some("MSL") | Some("ALT") => Reference::Amsl,
+        Some("AGL") | Some("SFC") => Reference::Agl,
+        _ => return Err(err()),
+    };
+    if parts.next().is_some() {
+        return Err(err());
+    }
+
+    Ok(Altitude {
+        value,
+        unit: Unit::Feet,
+        reference,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn altitude(value: u32, unit: Unit, reference: Reference) -> Altitude {
+        Altitude {
+            value,
+            unit,
+            reference,
+        }
+    }
+
+    #[test]
+    fn parse_formats() {
+        for (level, expected) in [
+            (
+                "FL195",
+                altitude(195, Unit::FlightLevel, Reference::Standard),
+            ),
+            (
+                "fl 65",
+                altitude(65, Unit::FlightLevel, Reference::Standard),
+            ),
+            ("3000 ft", altitude(3000, Unit::Feet, Reference::Amsl)),
+            ("2000 ft amsl", altitude(2000, Unit::Feet, Reference::Amsl)),
+            ("1500 ft AGL", altitude(1500, Unit::Feet, Reference::Agl)),
+            ("SFC", altitude(0, Unit::Feet, Reference::Agl)),
+            ("GND", altitude(0, Unit::Feet, Reference::Agl)),
+            ("UNL", altitude(999, Unit::FlightLevel, Reference::Standard)),
+            (
+                "unlimited",
+                altitude(999, Unit::FlightLevel, Reference::Standard),
+            ),
+        ] {
+            assert_eq!(parse_altitude(level), Ok(expected), "{}", level);
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for level in ["", "FL", "FLx", "3000", "3000 m", "3000 ft agl msl"] {
+            assert_eq!(parse_altitude(level), Err(AltError(level.to_string())));
+        }
+        assert_eq!(
+            AltError("3000 m".to_string()).to_string(),
+            "invalid altitude \"3000 m\""
+        );
+    }
+
+    #[test]
+    fn surface_unlimited() {
+        let sfc = parse_altitude("SFC").unwrap();
+        assert!(sfc.is_surface() && !sfc.is_unlimited());
+        assert_eq!(sfc.flight_level(), 0);
+
+        let unl = parse_altitude("UNL").unwrap();
+        assert!(unl.is_unlimited() && !unl.is_surface());
+        assert_eq!(unl.flight_level(), 999);
+
+        assert_eq!(parse_altitude("3050 ft").unwrap().flight_level(), 30);
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::state::Settings;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use std::collections::HashSet;
+use std::fmt;
+
+// Maximum number of LOA/RAT/Wave/Exclude entries, to keep QR codes scannable
+const MAX_ENTRIES: usize = 20;
+
+// Errors decoding compact settings
+#[derive(Debug)]
+pub enum CompactError {
+    Base64(base64::DecodeError),
+    Utf8,
+}
+
+impl fmt::Display for CompactError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompactError::Base64(e) => write!(f, "invalid settings code: {}", e),
+            CompactError::Utf8 => write!(f, "invalid settings code: not text"),
+        }
+    }
+}
+
+// First entries (in sorted order) of a selection
+fn cap(selection: &HashSet<String>) -> HashSet<String> {
+    let mut names = selection.iter().cloned().collect::<Vec<String>>();
+    names.sort();
+    names.into_iter().take(MAX_ENTRIES).collect()
+}
+
+impl Settings {
+    // Compact settings code, e.g. for a QR code. The code is the base64url
+    // encoded query string, so options at their default are left out and
+    // codes stay readable as new options are added
+    pub fn to_base64(&self) -> String {
+        let mut settings = self.clone();
+        settings.loa = cap(&self.loa);
+        settings.rat = self.rat.iter().take(MAX_ENTRIES).cloned().collect();
+        settings.wave = cap(&self.wave);
+        settings.exclude = cap(&self.exclude);
+        settings.gliding_sites = cap(&self.gliding_sites);
+
+        URL_SAFE_NO_PAD.encode(settings.to_query_string())
+    }
+
+    // Decode settings code
+    pub fn from_base64(text: &str) -> Result<Settings, CompactError> {
+        let data = URL_SAFE_NO_PAD
+            .decode(text.trim())
+            .map_err(CompactError::Base64)?;
+        let query = String::from_utf8(data).map_err(|_| CompactError::Utf8)?;
+
+        Ok(Settings::from_query_string(&query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{AirType, Format};
+
+    #[test]
+    fn round_trip() {
+        let mut settings = Settings {
+            atz: AirType::ClassD,
+            max_level: 195,
+            format: Format::Tnp,
+            rat: vec!["RAT B".to_string(), "RAT A".to_string()],
+            ..Settings::default()
+        };
+        settings.loa.insert("CAMBRIDGE RAZ".to_string());
+        settings.wave.insert("EDEN WAVE".to_string());
+
+        let code = settings.to_base64();
+        assert!(code
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+        assert_eq!(Settings::from_base64(&code).unwrap(), settings);
+        assert_eq!(Settings::from_base64("").unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn capped() {
+        let mut settings = Settings::default();
+        for n in 0..30 {
+            settings.loa.insert(format!("LOA {:02}", n));
+            settings.rat.push(format!("RAT {:02}", n));
+        }
+
+        let decoded = Settings::from_base64(&settings.to_base64()).unwrap();
+        assert_eq!(decoded.loa.len(), MAX_ENTRIES);
+        assert!(decoded.loa.contains("LOA 19") && !decoded.loa.contains("LOA 20"));
+        assert_eq!(decoded.rat, settings.rat[..MAX_ENTRIES]);
+    }
+
+    #[test]
+    fn malformed() {
+        for code in ["not base64!", "a", "+/+/"] {
+            assert!(matches!(
+                Settings::from_base64(code),
+                Err(CompactError::Base64(_))
+            ));
+        }
+        let code = URL_SAFE_NO_PAD.encode([0xff, 0xfe]);
+        assert!(matches!(
+            Settings::from_base64(&code),
+            Err(CompactError::Utf8)
+        ));
+    }
+}
@@ -19,4 +19,5 @@ pub mod extra_panel;
 pub mod extra_tab;
 pub mod notam_tab;
 pub mod options_tab;
+pub mod profile_tab;
 pub mod tabs;
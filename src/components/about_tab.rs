@@ -20,7 +20,7 @@ pub fn about_tab() -> Html {
     html! {
         <div class="content">
           <h3 class="subtitle">{ "Output Format" }</h3>
-            <p>{ "ASSelect generates airspace data for the mainland UK in OpenAir format.
+            <p>{ "ASSelect generates airspace data for the mainland UK in OpenAir format, with TNP, GeoJSON and KML also available. GPX and CUP formats give waypoints only, and CSV gives a list of the selected airspace.
               \"Competition\" format is intended for competition organiser use only." }</p>
           <h3 class="subtitle">{ "Airspace Types" }</h3>
             <p><em>{ "Non-ATZ Airfields" }</em>{ " are busy training aerodromes (without an ATZ) as listed
@@ -44,8 +44,8 @@ pub fn about_tab() -> Html {
               { ". With the exception of the Cambridge RAZ a local club briefing is
               required before using any local agreement." }</p>
           <h3 class="subtitle">{ "Radio Frequencies" }</h3>
-            <p>{ "Radio frequencies are stored using the OpenAir \"AF\" record type. In addition ASSelect
-              can add them to the airspace name. "}</p>
+            <p>{ "When selected, radio frequencies and station callsigns are stored using the OpenAir
+              \"AF\" and \"AG\" record types, and the frequency is added to the airspace name. "}</p>
           <h3 class="subtitle">{ "Temporary Restrictions, RA(T)" }</h3>
             <p>{ "RA(T)s are detailed in Mauve AICs on the "}
               <a class="text-primary"
@@ -14,7 +14,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
 use crate::state::{AirType, Settings};
-use crate::AirspaceSetting;
+use crate::{AirspaceSetting, SiteSetting};
 use web_sys::HtmlInputElement;
 use yew::{function_component, html, Callback, Event, Html, Properties, TargetCast};
 
@@ -23,6 +23,8 @@ pub struct Props {
     pub settings: Settings,
     pub gliding_sites: Vec<String>,
     pub callback: Callback<AirspaceSetting>,
+    pub site_callback: Callback<SiteSetting>,
+    pub clear_sites_callback: Callback<()>,
 }
 
 #[function_component(AirspaceTab)]
@@ -37,6 +39,18 @@ pub fn airspace_tab(props: &Props) -> Html {
 
     let set = &props.settings;
 
+    let home_coords = set
+        .home_coords
+        .map(|(lat, lon)| format!("{},{}", lat, lon))
+        .unwrap_or_default();
+
+    let bbox = set
+        .bbox
+        .map(|(min_lat, min_lon, max_lat, max_lon)| {
+            format!("{},{},{},{}", min_lat, min_lon, max_lat, max_lon)
+        })
+        .unwrap_or_default();
+
     let gliding_sites = || {
         props
             .gliding_sites
@@ -49,6 +63,33 @@ pub fn airspace_tab(props: &Props) -> Html {
             .collect::<Html>()
     };
 
+    let onsite = props.site_callback.reform(|e: Event| {
+        let element = e.target_unchecked_into::<HtmlInputElement>();
+
+        SiteSetting {
+            name: element.name(),
+            checked: element.checked(),
+        }
+    });
+
+    let onclear_sites = props.clear_sites_callback.reform(|_| ());
+
+    // Gliding sites to include, all of them if none are ticked
+    let site_checkboxes = props
+        .gliding_sites
+        .iter()
+        .map(|name| {
+            html! {
+                <div class="column is-one-quarter">
+                  <label class="checkbox">
+                    <input type="checkbox" class="mr-2" name={name.clone()} checked={set.gliding_sites.contains(name)} onchange={onsite.clone()}/>
+                    {name}
+                  </label>
+                </div>
+            }
+        })
+        .collect::<Html>();
+
     html! {
         <div class="box">
           <div class="columns">
@@ -84,6 +125,23 @@ pub fn airspace_tab(props: &Props) -> Html {
                 </label>
               </div>
             </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"MATZ"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="matz" onchange={onchange.clone()}>
+                        <option value="exclude" selected={set.matz.is_none()}>{"No"}</option>
+                        <option value="matz" selected={set.matz == Some(AirType::Matz)}>{"MATZ"}</option>
+                        <option value="classg" selected={set.matz == Some(AirType::ClassG)}>{"Class G"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
           </div>
 
           <div class="columns">
@@ -156,7 +214,66 @@ pub fn airspace_tab(props: &Props) -> Html {
                 </label>
               </div>
             </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Limit to Home Radius"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="home_radius" onchange={onchange.clone()}>
+                        <option value="no" selected={set.home_radius_nm.is_none()}>{"No"}</option>
+                        <option value="30" selected={set.home_radius_nm == Some(30)}>{"30 nm"}</option>
+                        <option value="50" selected={set.home_radius_nm == Some(50)}>{"50 nm"}</option>
+                        <option value="100" selected={set.home_radius_nm == Some(100)}>{"100 nm"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
           </div>
+
+          <div class="columns">
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Home Position (lat,lon)"}
+                  <div class="control">
+                    <input class="input" type="text" name="home_coords" placeholder="52.1,-1.2"
+                      value={home_coords} onchange={onchange.clone()}/>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Bounding Box (lat,lon,lat,lon)"}
+                  <div class="control">
+                    <input class="input" type="text" name="bbox" placeholder="51.5,-2.0,52.5,-0.5"
+                      value={bbox} onchange={onchange.clone()}/>
+                  </div>
+                </label>
+              </div>
+            </div>
+          </div>
+
+          if set.gliding.is_some() {
+            <div class="field">
+              <label class="label">
+                {"Gliding Sites"}
+                <button class="button is-small is-info is-light ml-2" onclick={onclear_sites} disabled={set.gliding_sites.is_empty()}>
+                  {"All Sites"}
+                </button>
+              </label>
+              <p class="help mb-2">{"Only ticked sites are included, or every site if none are ticked"}</p>
+              <div class="columns is-multiline">
+                { site_checkboxes }
+              </div>
+            </div>
+          }
         </div>
     }
 }
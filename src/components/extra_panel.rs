@@ -13,10 +13,10 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
-use crate::{ExtraCategory, ExtraSetting};
+use crate::{ExtraCategory, ExtraSetting, GroupSetting, MoveSetting, RangeSetting};
 use std::collections::HashSet;
 use web_sys::HtmlInputElement;
-use yew::{function_component, html, Callback, Event, Html, Properties, TargetCast};
+use yew::{function_component, html, use_state, Callback, Event, Html, Properties, TargetCast};
 
 #[derive(Properties, PartialEq)]
 pub struct Props {
@@ -24,6 +24,16 @@ pub struct Props {
     pub names: Vec<String>,
     pub selected: HashSet<String>,
     pub callback: Callback<ExtraSetting>,
+    #[prop_or_default]
+    pub order: Vec<String>,
+    #[prop_or_default]
+    pub move_callback: Callback<MoveSetting>,
+    #[prop_or_default]
+    pub range_callback: Option<Callback<RangeSetting>>,
+    #[prop_or_default]
+    pub groups: Vec<(String, Vec<String>)>,
+    #[prop_or_default]
+    pub group_callback: Callback<GroupSetting>,
 }
 
 #[function_component(ExtraPanel)]
@@ -40,7 +50,94 @@ pub fn extra_panel(props: &Props) -> Html {
         }
     });
 
+    // Select/clear all names with a prefix
+    let prefix = use_state(String::new);
+    let onprefix = {
+        let prefix = prefix.clone();
+        Callback::from(move |e: Event| {
+            prefix.set(e.target_unchecked_into::<HtmlInputElement>().value())
+        })
+    };
+    let onrange = |callback: &Callback<RangeSetting>, checked| {
+        let prefix = prefix.trim().to_string();
+        callback.reform(move |_| RangeSetting {
+            prefix: prefix.clone(),
+            checked,
+        })
+    };
+
+    // Group checkboxes, checked when every member is selected
+    let groups = props
+        .groups
+        .iter()
+        .map(|(group, members)| {
+            let checked = members.iter().all(|name| props.selected.contains(name));
+            let ongroup = {
+                let group = group.clone();
+                let members = members.clone();
+                props.group_callback.reform(move |e: Event| GroupSetting {
+                    group: group.clone(),
+                    checked: e.target_unchecked_into::<HtmlInputElement>().checked(),
+                    members: members.clone(),
+                })
+            };
+
+            html! {
+                <label class="checkbox mr-4">
+                  <input type="checkbox" class="mr-2" {checked} onchange={ongroup}/>
+                  {format!("All {} ({})", group, members.len())}
+                </label>
+            }
+        })
+        .collect::<Html>();
+
+    // Output order of selected names, with up/down buttons
+    let last = props.order.len().saturating_sub(1);
+    let order = props
+        .order
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let onmove = |to_index: usize| {
+                let name = name.clone();
+                props.move_callback.reform(move |_| MoveSetting {
+                    name: name.clone(),
+                    to_index,
+                })
+            };
+
+            html! {
+                <tr>
+                  <td>{name}</td>
+                  <td>
+                    <button class="button is-small is-text" disabled={index == 0} onclick={onmove(index.saturating_sub(1))}>{"Up"}</button>
+                    <button class="button is-small is-text" disabled={index == last} onclick={onmove(index + 1)}>{"Down"}</button>
+                  </td>
+                </tr>
+            }
+        })
+        .collect::<Html>();
+
     html! {
+        <>
+        if let Some(callback) = &props.range_callback {
+          <div class="field has-addons">
+            <div class="control">
+              <input class="input is-small" type="text" placeholder="Name prefix" value={(*prefix).clone()} onchange={onprefix}/>
+            </div>
+            <div class="control">
+              <button class="button is-small is-info is-light" disabled={prefix.trim().is_empty()} onclick={onrange(callback, true)}>{"Select"}</button>
+            </div>
+            <div class="control">
+              <button class="button is-small is-info is-light" disabled={prefix.trim().is_empty()} onclick={onrange(callback, false)}>{"Clear"}</button>
+            </div>
+          </div>
+        }
+
+        if !props.groups.is_empty() {
+          <div class="field">{ groups }</div>
+        }
+
         <div class="columns is-multiline">
         {
             props.names.iter().map(|name| {
@@ -58,5 +155,15 @@ pub fn extra_panel(props: &Props) -> Html {
             }).collect::<Html>()
         }
         </div>
+
+        if props.order.len() > 1 {
+          <label class="label">{"Output Order"}</label>
+          <table class="table is-narrow">
+            <tbody>
+              { order }
+            </tbody>
+          </table>
+        }
+        </>
     }
 }
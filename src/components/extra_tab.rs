@@ -13,6 +13,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
+use crate::state::SelectionKind;
 use crate::ExtraCategory;
 use yew::{function_component, html, use_state, AttrValue, Callback, Children, Html, Properties};
 
@@ -22,6 +23,10 @@ pub struct Props {
     pub names: Vec<AttrValue>,
     pub categories: Vec<ExtraCategory>,
     pub on_clear: Callback<ExtraCategory>,
+    pub on_clear_all: Callback<()>,
+    pub on_invert: Callback<ExtraCategory>,
+    pub on_select_all: Callback<ExtraCategory>,
+    pub selection: Vec<(SelectionKind, String)>,
 }
 
 #[function_component(ExtraTab)]
@@ -39,6 +44,20 @@ pub fn extra_tab(props: &Props) -> Html {
         move |_| onclear.emit(category)
     };
 
+    let onselect_all = {
+        let category = props.categories[*active_panel];
+        let onselect_all = props.on_select_all.clone();
+        move |_| onselect_all.emit(category)
+    };
+
+    let oninvert = {
+        let category = props.categories[*active_panel];
+        let oninvert = props.on_invert.clone();
+        move |_| oninvert.emit(category)
+    };
+
+    let onclear_all = props.on_clear_all.reform(|_| ());
+
     let iter = props.names.iter().zip(props.children.iter()).enumerate();
     let panels = iter
         .map(|(n, (name, child))| {
@@ -51,6 +70,8 @@ pub fn extra_tab(props: &Props) -> Html {
                       if n == *active_panel {
                         html! {
                           <div class="card-header-icon">
+                            <input class="button is-info is-light is-small ml-2" type="button" onclick={onselect_all.clone()} value="All" />
+                            <input class="button is-info is-light is-small ml-2" type="button" onclick={oninvert.clone()} value="Invert" />
                             <input class="button is-info is-light is-small ml-2" type="button" onclick={onclear.clone()} value="Clear" />
                           </div>
                         }
@@ -68,9 +89,40 @@ pub fn extra_tab(props: &Props) -> Html {
         })
         .collect::<Html>();
 
+    // Review of selected names
+    let selection = props
+        .selection
+        .iter()
+        .map(|(kind, name)| {
+            html! {
+                <tr>
+                  <td>{kind.label()}</td>
+                  <td>{name}</td>
+                </tr>
+            }
+        })
+        .collect::<Html>();
+
     html! {
         <div>
           { panels }
+          if !props.selection.is_empty() {
+            <div class="card block">
+              <header class="card-header">
+                <p class="card-header-title">{"Your Selection"}</p>
+              </header>
+              <div class="card-content">
+                <table class="table is-narrow">
+                  <tbody>
+                    { selection }
+                  </tbody>
+                </table>
+              </div>
+            </div>
+          }
+          <button class="button is-text" onclick={onclear_all}>
+            {"Clear LOA, RAT and Wave"}
+          </button>
         </div>
     }
 }
@@ -13,8 +13,11 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
-use crate::state::{AirType, Format, Overlay, Settings};
-use crate::AirspaceSetting;
+use crate::state::{
+    airtype_label, parse_format, AirType, AltitudeRef, Format, MaxLevelPreset, Overlay, Settings,
+    WaveClip,
+};
+use crate::{AirspaceSetting, OverlaySetting, TypeCapSetting, TypeSetting};
 use web_sys::HtmlInputElement;
 use yew::{function_component, html, Callback, Event, Html, Properties, TargetCast};
 
@@ -22,6 +25,13 @@ use yew::{function_component, html, Callback, Event, Html, Properties, TargetCas
 pub struct Props {
     pub settings: Settings,
     pub callback: Callback<AirspaceSetting>,
+    pub overlay_callback: Callback<OverlaySetting>,
+    pub clear_overlay_callback: Callback<()>,
+    pub format_callback: Callback<Format>,
+    pub max_level_callback: Callback<MaxLevelPreset>,
+    pub radio_callback: Callback<bool>,
+    pub type_callback: Callback<TypeSetting>,
+    pub type_cap_callback: Callback<TypeCapSetting>,
 }
 
 #[function_component(OptionsTab)]
@@ -33,8 +43,100 @@ pub fn options_tab(props: &Props) -> Html {
         AirspaceSetting { name, value }
     });
 
+    let onformat = {
+        let callback = props.format_callback.clone();
+        Callback::from(move |e: Event| {
+            let value = e.target_unchecked_into::<HtmlInputElement>().value();
+            if let Some(format) = parse_format(&value) {
+                callback.emit(format)
+            }
+        })
+    };
+
+    let onmax_level = {
+        let callback = props.max_level_callback.clone();
+        Callback::from(move |e: Event| {
+            let value = e.target_unchecked_into::<HtmlInputElement>().value();
+            if let Some(preset) = MaxLevelPreset::ALL
+                .into_iter()
+                .find(|preset| preset.level().to_string() == value)
+            {
+                callback.emit(preset)
+            }
+        })
+    };
+
+    let onoverlay = props.overlay_callback.reform(|e: Event| {
+        let name = e.target_unchecked_into::<HtmlInputElement>().name();
+        let checked = e.target_unchecked_into::<HtmlInputElement>().checked();
+
+        OverlaySetting { name, checked }
+    });
+
+    let onclear_overlay = props.clear_overlay_callback.reform(|_| ());
+    let onradio = props
+        .radio_callback
+        .reform(|e: Event| e.target_unchecked_into::<HtmlInputElement>().checked());
+
     let set = &props.settings;
 
+    let max_levels = MaxLevelPreset::ALL
+        .into_iter()
+        .map(|preset| {
+            html! {
+                <option value={preset.level().to_string()} selected={set.max_level == preset.level()}>
+                  {preset.label()}
+                </option>
+            }
+        })
+        .collect::<Html>();
+
+    // Airspace type include/exclude and level cap rows
+    let type_rows = AirType::ALL
+        .into_iter()
+        .map(|air_type| {
+            let callback = props.type_callback.clone();
+            let ontype = Callback::from(move |e: Event| {
+                let enabled = e.target_unchecked_into::<HtmlInputElement>().checked();
+                callback.emit(TypeSetting { air_type, enabled })
+            });
+
+            let callback = props.type_cap_callback.clone();
+            let oncap = Callback::from(move |e: Event| {
+                let value = e.target_unchecked_into::<HtmlInputElement>().value();
+                callback.emit(TypeCapSetting {
+                    air_type,
+                    level: value.parse().ok(),
+                })
+            });
+
+            let cap = set.type_caps.get(&air_type).copied();
+
+            html! {
+                <tr>
+                  <td>
+                    <label class="checkbox">
+                      <input type="checkbox" class="mr-2" checked={set.types.get(&air_type) != Some(&false)} onchange={ontype}/>
+                      {airtype_label(air_type)}
+                    </label>
+                  </td>
+                  <td>
+                    <div class="select is-small">
+                      <select onchange={oncap}>
+                        <option value="no" selected={cap.is_none()}>{"No Cap"}</option>
+                        {
+                          [65, 105, 125, 195].into_iter().map(|level| html! {
+                            <option value={level.to_string()} selected={cap == Some(level)}>{format!("FL{}", level)}</option>
+                          }).collect::<Html>()
+                        }
+                      </select>
+                    </div>
+                  </td>
+                </tr>
+            }
+        })
+        .collect::<Html>();
+
     html! {
         <div class="box">
           <div class="columns">
@@ -44,10 +146,17 @@ pub fn options_tab(props: &Props) -> Html {
                   {"Format"}
                   <div class="control">
                     <div class="select is-fullwidth">
-                      <select name="format" onchange={onchange.clone()}>
+                      <select name="format" onchange={onformat}>
                         <option value="openair" selected={set.format == Format::OpenAir}>{"OpenAir"}</option>
+                        <option value="openair_lk" selected={set.format == Format::OpenAirLk}>{"OpenAir (LK8000)"}</option>
                         <option value="ratonly" selected={set.format == Format::RatOnly}>{"RA(T) only"}</option>
                         <option value="competition" selected={set.format == Format::Competition}>{"Competition"}</option>
+                        <option value="tnp" selected={set.format == Format::Tnp}>{"TNP"}</option>
+                        <option value="geojson" selected={set.format == Format::GeoJson}>{"GeoJSON"}</option>
+                        <option value="kml" selected={set.format == Format::Kml}>{"KML"}</option>
+                        <option value="gpx" selected={set.format == Format::Gpx}>{"GPX waypoints"}</option>
+                        <option value="cup" selected={set.format == Format::Cup}>{"CUP gliding sites"}</option>
+                        <option value="csv" selected={set.format == Format::Csv}>{"CSV index"}</option>
                       </select>
                     </div>
                   </div>
@@ -61,12 +170,26 @@ pub fn options_tab(props: &Props) -> Html {
                   {"Maximum Level"}
                   <div class="control">
                     <div class="select is-fullwidth">
-                      <select name="max_level" onchange={onchange.clone()}>
-                        <option value="660" selected={set.max_level == 660}>{"Unlimited"}</option>
-                        <option value="195" selected={set.max_level == 195}>{"FL195"}</option>
-                        <option value="125" selected={set.max_level == 125}>{"FL125"}</option>
-                        <option value="105" selected={set.max_level == 105}>{"FL105"}</option>
-                        <option value="65" selected={set.max_level == 65}>{"FL65"}</option>
+                      <select name="max_level" onchange={onmax_level}>
+                        { max_levels }
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Minimum Level"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="min_level" onchange={onchange.clone()}>
+                        <option value="0" selected={set.min_level == 0}>{"None"}</option>
+                        <option value="65" selected={set.min_level == 65}>{"FL65"}</option>
+                        <option value="105" selected={set.min_level == 105}>{"FL105"}</option>
+                        <option value="195" selected={set.min_level == 195}>{"FL195"}</option>
                       </select>
                     </div>
                   </div>
@@ -110,6 +233,26 @@ pub fn options_tab(props: &Props) -> Html {
                 </label>
               </div>
             </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Obstacle Minimum"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="obstacle_min_ft" onchange={onchange.clone()}>
+                        <option value="no" selected={set.obstacle_min_ft.is_none()}>{"All"}</option>
+                        {
+                          [300, 500, 1000].into_iter().map(|height| html! {
+                            <option value={height.to_string()} selected={set.obstacle_min_ft == Some(height)}>{format!("{} ft", height)}</option>
+                          }).collect::<Html>()
+                        }
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
           </div>
 
           <div class="columns">
@@ -117,11 +260,25 @@ pub fn options_tab(props: &Props) -> Html {
               <div class="field">
                 <label class="label">
                   {"Radio Frequency"}
+                </label>
+                <div class="control">
+                  <label class="checkbox">
+                    <input type="checkbox" class="mr-2" name="radio" checked={set.radio} onchange={onradio}/>
+                    {"Add to name"}
+                  </label>
+                </div>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Frequency Comment"}
                   <div class="control">
                     <div class="select is-fullwidth">
-                      <select name="radio" onchange={onchange.clone()}>
-                        <option value="no" selected={!set.radio}>{"No"}</option>
-                        <option value="yes" selected={set.radio}>{"Add to name"}</option>
+                      <select name="radio_as_comment" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.radio_as_comment}>{"No"}</option>
+                        <option value="yes" selected={set.radio_as_comment}>{"Yes"}</option>
                       </select>
                     </div>
                   </div>
@@ -133,19 +290,408 @@ pub fn options_tab(props: &Props) -> Html {
               <div class="field">
                 <label class="label">
                   {"Altitude Overlay"}
+                </label>
+                <div class="control">
+                  <label class="checkbox is-block">
+                    <input type="checkbox" class="mr-2" name="fl195" checked={set.overlay.contains(&Overlay::FL195)} onchange={onoverlay.clone()}/>
+                    {"Bases to FL195"}
+                  </label>
+                  <label class="checkbox is-block">
+                    <input type="checkbox" class="mr-2" name="fl105" checked={set.overlay.contains(&Overlay::FL105)} onchange={onoverlay.clone()}/>
+                    {"Bases to FL105"}
+                  </label>
+                  <label class="checkbox is-block">
+                    <input type="checkbox" class="mr-2" name="atzdz" checked={set.overlay.contains(&Overlay::AtzDz)} onchange={onoverlay.clone()}/>
+                    {"Bases to FL105 and ATZ/DZ"}
+                  </label>
+                  <button class="button is-small is-text px-0" onclick={onclear_overlay} disabled={set.overlay.is_empty()}>
+                    {"No overlay"}
+                  </button>
+                </div>
+              </div>
+            </div>
+          </div>
+
+          <div class="columns">
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"RATs From"}
+                  <div class="control">
+                    <input class="input" type="date" name="rat_from"
+                      value={set.rat_from.clone().unwrap_or_default()} onchange={onchange.clone()}/>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Airspace Colours"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="colorize" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.colorize}>{"No"}</option>
+                        <option value="yes" selected={set.colorize}>{"Yes"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Low Airspace Only"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="low_only" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.low_only}>{"No"}</option>
+                        <option value="yes" selected={set.low_only}>{"Base to 3000 ft"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+          </div>
+
+          <div class="columns">
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Altitude Reference"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="altitude_ref" onchange={onchange.clone()}>
+                        <option value="asis" selected={set.altitude_ref == AltitudeRef::AsIs}>{"As source"}</option>
+                        <option value="agl" selected={set.altitude_ref == AltitudeRef::PreferAgl}>{"SFC as 0 ft AGL"}</option>
+                        <option value="amsl" selected={set.altitude_ref == AltitudeRef::PreferAmsl}>{"Mark altitudes AMSL"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Name Prefix"}
+                  <div class="control">
+                    <input class="input" type="text" name="name_prefix" placeholder="e.g. [YCC]"
+                      value={set.name_prefix.clone().unwrap_or_default()} onchange={onchange.clone()}/>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Active Now Only"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="active_now" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.active_now}>{"No"}</option>
+                        <option value="yes" selected={set.active_now}>{"Yes"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+          </div>
+
+          <div class="columns">
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Line Endings"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="crlf" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.crlf}>{"Unix (LF)"}</option>
+                        <option value="yes" selected={set.crlf}>{"Windows (CRLF)"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Feature Limit"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="max_features" onchange={onchange.clone()}>
+                        <option value="no" selected={set.max_features.is_none()}>{"No Limit"}</option>
+                        {
+                          [250, 500, 1000].into_iter().map(|max| html! {
+                            <option value={max.to_string()} selected={set.max_features == Some(max)}>{max}</option>
+                          }).collect::<Html>()
+                        }
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Number Duplicate Names"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="dedupe_names" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.dedupe_names}>{"No"}</option>
+                        <option value="yes" selected={set.dedupe_names}>{"Yes"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+          </div>
+
+          <div class="columns">
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"File Per Type"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="split_by_type" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.split_by_type}>{"No"}</option>
+                        <option value="yes" selected={set.split_by_type}>{"Yes (ZIP)"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Frequency Decimals"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="freq_decimals" onchange={onchange.clone()}>
+                        <option value="3" selected={set.freq_decimals == 3}>{"3 (123.455)"}</option>
+                        <option value="2" selected={set.freq_decimals == 2}>{"2 (123.45)"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Notes"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="include_notes" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.include_notes}>{"No"}</option>
+                        <option value="yes" selected={set.include_notes}>{"As Comments"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+          </div>
+
+          <div class="columns">
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Author"}
+                  <div class="control">
+                    <input class="input" type="text" name="author" placeholder="Your name"
+                      value={set.author.clone().unwrap_or_default()} onchange={onchange.clone()}/>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Wave Minimum"}
                   <div class="control">
                     <div class="select is-fullwidth">
-                      <select name="overlay" onchange={onchange.clone()}>
-                        <option value="no" selected={set.overlay.is_none()}>{"No"}</option>
-                        <option value="fl195" selected={set.overlay == Some(Overlay::FL195)}>{"Bases to FL195"}</option>
-                        <option value="fl105" selected={set.overlay == Some(Overlay::FL105)}>{"Bases to FL105"}</option>
-                        <option value="atzdz" selected={set.overlay == Some(Overlay::AtzDz)}>{"Bases to FL105 and ATZ/DZ"}</option>
+                      <select name="wave_min_fl" onchange={onchange.clone()}>
+                        <option value="no" selected={set.wave_min_fl.is_none()}>{"Source Limits"}</option>
+                        {
+                          [55, 85, 105, 145].into_iter().map(|level| html! {
+                            <option value={level.to_string()} selected={set.wave_min_fl == Some(level)}>{format!("FL{}", level)}</option>
+                          }).collect::<Html>()
+                        }
                       </select>
                     </div>
                   </div>
                 </label>
               </div>
             </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Circle Segments"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="arc_resolution" onchange={onchange.clone()}>
+                        {
+                          [16, 32, 64, 128].into_iter().map(|resolution| html! {
+                            <option value={resolution.to_string()} selected={set.arc_resolution == resolution}>{resolution.to_string()}</option>
+                          }).collect::<Html>()
+                        }
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+          </div>
+
+          <div class="columns">
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Frequency Required"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="require_frequency" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.require_frequency}>{"No"}</option>
+                        <option value="yes" selected={set.require_frequency}>{"Yes"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Competition Centre"}
+                  <div class="control">
+                    <input class="input" type="text" name="competition_center" placeholder="Gliding site"
+                      value={set.competition_center.clone().unwrap_or_default()} onchange={onchange.clone()}/>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Competition Radius"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="competition_radius" onchange={onchange.clone()}>
+                        <option value="no" selected={set.competition_radius_nm.is_none()}>{"No Limit"}</option>
+                        {
+                          [50, 100, 150, 200].into_iter().map(|radius| html! {
+                            <option value={radius.to_string()} selected={set.competition_radius_nm == Some(radius)}>{format!("{} nm", radius)}</option>
+                          }).collect::<Html>()
+                        }
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+          </div>
+
+          <div class="columns">
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Label Points"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="label_points" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.label_points}>{"No"}</option>
+                        <option value="yes" selected={set.label_points}>{"Yes"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"ATZ Buffer"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="atz_buffer" onchange={onchange.clone()}>
+                        <option value="no" selected={set.atz_buffer_nm.is_none()}>{"None"}</option>
+                        {
+                          [0.5, 1.0].into_iter().map(|buffer| html! {
+                            <option value={buffer.to_string()} selected={set.atz_buffer_nm == Some(buffer)}>{format!("{} nm", buffer)}</option>
+                          }).collect::<Html>()
+                        }
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Wave Above Maximum"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="wave_clip" onchange={onchange.clone()}>
+                        <option value="clip" selected={set.wave_clip == WaveClip::Clip}>{"Clip"}</option>
+                        <option value="drop" selected={set.wave_clip == WaveClip::Drop}>{"Drop"}</option>
+                        <option value="keep" selected={set.wave_clip == WaveClip::Keep}>{"Keep"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+          </div>
+
+          <div class="columns">
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Inactive Danger Areas"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select name="exclude_inactive_danger" onchange={onchange.clone()}>
+                        <option value="no" selected={!set.exclude_inactive_danger}>{"Include"}</option>
+                        <option value="yes" selected={set.exclude_inactive_danger}>{"Exclude"}</option>
+                      </select>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+          </div>
+
+          <div class="field">
+            <label class="label">{"Airspace Types"}</label>
+            <table class="table is-narrow">
+              <tbody>
+                { type_rows }
+              </tbody>
+            </table>
           </div>
         </div>
     }
@@ -0,0 +1,212 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::{function_component, html, use_node_ref, Callback, Event, Html, Properties, TargetCast};
+
+// Options that can be kept when merging, with labels
+const KEEP_OPTIONS: [(&str, &str); 8] = [
+    ("atz", "ATZ"),
+    ("matz", "MATZ"),
+    ("gliding", "Gliding Airfield"),
+    ("max_level", "Maximum Level"),
+    ("min_level", "Minimum Level"),
+    ("radio", "Radio Frequency"),
+    ("overlay", "Altitude Overlay"),
+    ("format", "Format"),
+];
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub names: Vec<String>,
+    pub active: Option<String>,
+    pub on_save: Callback<String>,
+    pub on_load: Callback<String>,
+    pub on_import: Callback<String>,
+    pub on_merge: Callback<String>,
+    pub on_restore: Callback<String>,
+    pub touched: Vec<String>,
+    pub on_touch: Callback<String>,
+    pub import_error: Option<String>,
+    pub code: String,
+}
+
+#[function_component(ProfileTab)]
+pub fn profile_tab(props: &Props) -> Html {
+    let input_node_ref = use_node_ref();
+
+    let onsave = {
+        let input_node_ref = input_node_ref.clone();
+        let on_save = props.on_save.clone();
+        Callback::from(move |_| {
+            if let Some(input) = input_node_ref.cast::<HtmlInputElement>() {
+                let name = input.value().trim().to_string();
+                if !name.is_empty() {
+                    on_save.emit(name);
+                    input.set_value("");
+                }
+            }
+        })
+    };
+
+    let import_node_ref = use_node_ref();
+
+    let onimport = {
+        let import_node_ref = import_node_ref.clone();
+        let on_import = props.on_import.clone();
+        Callback::from(move |_| {
+            if let Some(input) = import_node_ref.cast::<HtmlTextAreaElement>() {
+                on_import.emit(input.value());
+            }
+        })
+    };
+
+    let onmerge = {
+        let import_node_ref = import_node_ref.clone();
+        let on_merge = props.on_merge.clone();
+        Callback::from(move |_| {
+            if let Some(input) = import_node_ref.cast::<HtmlTextAreaElement>() {
+                on_merge.emit(input.value());
+            }
+        })
+    };
+
+    let onrestore = {
+        let import_node_ref = import_node_ref.clone();
+        let on_restore = props.on_restore.clone();
+        Callback::from(move |_| {
+            if let Some(input) = import_node_ref.cast::<HtmlTextAreaElement>() {
+                on_restore.emit(input.value());
+            }
+        })
+    };
+
+    let ontouch = props
+        .on_touch
+        .reform(|e: Event| e.target_unchecked_into::<HtmlInputElement>().value());
+
+    let onchange = props
+        .on_load
+        .reform(|e: Event| e.target_unchecked_into::<HtmlInputElement>().value());
+
+    let profiles = || {
+        props
+            .names
+            .iter()
+            .map(|name| {
+                html! {
+                    <option selected={props.active.as_ref() == Some(name)}>{name}</option>
+                }
+            })
+            .collect::<Html>()
+    };
+
+    html! {
+        <div class="box">
+          <div class="columns">
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Save Profile"}
+                  <div class="field has-addons">
+                    <div class="control is-expanded">
+                      <input ref={input_node_ref} class="input" type="text" placeholder="Profile name" />
+                    </div>
+                    <div class="control">
+                      <button class="button is-info" onclick={onsave}>{"Save"}</button>
+                    </div>
+                  </div>
+                </label>
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Load Profile"}
+                  <div class="control">
+                    <div class="select is-fullwidth">
+                      <select {onchange}>
+                        <option selected={props.active.is_none()} disabled=true value="">{"Select..."}</option>
+                        { profiles() }
+                      </select>
+                    </div>
+                  </div>
+                </label>
+                if let Some(active) = &props.active {
+                  <p class="help">{format!("Current settings match profile {}", active)}</p>
+                }
+              </div>
+            </div>
+
+            <div class="column is-one-third">
+              <div class="field">
+                <label class="label">
+                  {"Import Settings"}
+                  <div class="control">
+                    <textarea ref={import_node_ref} class="textarea" rows="3" placeholder="Paste settings JSON, code or session"></textarea>
+                  </div>
+                </label>
+                if let Some(error) = &props.import_error {
+                  <p class="help is-danger">{error}</p>
+                }
+              </div>
+              <div class="field is-grouped">
+                <div class="control">
+                  <button class="button is-info" onclick={onimport}>{"Import"}</button>
+                </div>
+                <div class="control">
+                  <button class="button is-info is-light" onclick={onmerge} title="Add JSON settings, e.g. club defaults, keeping options you have set">{"Merge"}</button>
+                </div>
+                <div class="control">
+                  <button class="button is-info is-light" onclick={onrestore} title="Restore a saved session file">{"Restore Session"}</button>
+                </div>
+              </div>
+            </div>
+          </div>
+
+          <div class="field">
+            <label class="label">
+              {"Keep When Merging"}
+              <div class="control">
+                <div class="select">
+                  <select onchange={ontouch}>
+                    <option selected=true disabled=true value="">{"Add option..."}</option>
+                    {
+                      KEEP_OPTIONS.into_iter().map(|(name, label)| html! {
+                        <option value={name} disabled={props.touched.iter().any(|touched| touched == name)}>{label}</option>
+                      }).collect::<Html>()
+                    }
+                  </select>
+                </div>
+              </div>
+            </label>
+            if !props.touched.is_empty() {
+              <p class="help">{format!("Merges won't change: {}", props.touched.join(", "))}</p>
+            }
+          </div>
+
+          <div class="field">
+            <label class="label">
+              {"Settings Code"}
+              <div class="control">
+                <input class="input is-family-monospace" type="text" readonly=true value={props.code.clone()} />
+              </div>
+            </label>
+            <p class="help">{"Import this code to copy the current settings, e.g. from a QR code"}</p>
+          </div>
+        </div>
+    }
+}
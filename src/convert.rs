@@ -13,13 +13,56 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
-use crate::state::{AirType, Format, Settings};
+use crate::altitude::{parse_altitude, Altitude, Reference, Unit};
+use crate::geometry::{
+    boundary_ring, centroid, intersects_box, intersects_circle, parse_distance, parse_latlon,
+    LatLon, ARC_RESOLUTION,
+};
+use crate::state::{
+    airtype_value, format_value, parse_date, AirType, AltitudeRef, Format, Settings, WaveClip,
+};
 use crate::yaixm::{
     Arc, Boundary, Circle, Feature, IcaoClass, IcaoType, Loa, LocalType, Obstacle, Rule, Service,
     Volume, Yaixm,
 };
-use chrono::Utc;
+use chrono::{DateTime, Timelike, Utc};
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+pub mod csv;
+pub mod cup;
+pub mod geojson;
+pub mod gpx;
+pub mod kml;
+#[cfg(test)]
+mod parse;
+pub mod summary;
+pub mod tnp;
+
+// Maximum edit distance for approximate home airfield name match
+const HOME_MATCH_DISTANCE: usize = 2;
+
+// Highest base (in hundreds of feet) for low airspace only option
+const LOW_ONLY_LEVEL: u16 = 30;
+
+// Errors generating airspace data
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WriterError {
+    MissingGeometry(String),
+    BadAltitude { name: String, level: String },
+}
+
+impl fmt::Display for WriterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriterError::MissingGeometry(name) => write!(f, "no geometry for \"{}\"", name),
+            WriterError::BadAltitude { name, level } => {
+                write!(f, "invalid level \"{}\" for \"{}\"", level, name)
+            }
+        }
+    }
+}
 
 impl IcaoClass {
     fn as_str(&self) -> &'static str {
@@ -58,6 +101,7 @@ impl LocalType {
 impl Rule {
     fn as_str(&self) -> &'static str {
         match self {
+            Rule::Inactive => "INACTIVE",
             Rule::Intense => "INTENSE",
             Rule::Loa => "LOA",
             Rule::NoSsr => "NOSSR",
@@ -71,49 +115,94 @@ impl Rule {
     }
 }
 
-impl AirType {
-    fn as_str(&self) -> &'static str {
-        match self {
-            AirType::ClassA => "A",
-            AirType::ClassB => "B",
-            AirType::ClassC => "C",
-            AirType::ClassD => "D",
-            AirType::ClassE => "E",
-            AirType::ClassF => "F",
-            AirType::ClassG => "G",
-            AirType::Prohibited => "P",
-            AirType::Danger => "Q",
-            AirType::Restricted => "R",
-            AirType::Gliding => "W",
-            AirType::Cta => "CTA",
-            AirType::Ctr => "CTR",
-            AirType::Matz => "MATZ",
-            AirType::Other => "OTHER",
-            AirType::Rmz => "RMZ",
-            AirType::Tmz => "RMZ",
-        }
+// OpenAir AC class token for airspace type
+fn openair_class(air_type: AirType) -> &'static str {
+    match air_type {
+        // UK airways are Class A
+        AirType::Awy => "A",
+        AirType::ClassA => "A",
+        AirType::ClassB => "B",
+        AirType::ClassC => "C",
+        AirType::ClassD => "D",
+        AirType::ClassE => "E",
+        AirType::ClassF => "F",
+        AirType::ClassG => "G",
+        AirType::Prohibited => "P",
+        AirType::Danger => "Q",
+        AirType::Restricted => "R",
+        AirType::Gliding => "W",
+        AirType::Cta => "CTA",
+        AirType::Ctr => "CTR",
+        AirType::Matz => "MATZ",
+        AirType::Other => "OTHER",
+        AirType::Rmz => "RMZ",
+        AirType::Tmz => "TMZ",
+    }
+}
+
+// Airspace type from OpenAir class token
+fn class_airtype(class: &str) -> AirType {
+    match class {
+        "A" => AirType::ClassA,
+        "B" => AirType::ClassB,
+        "C" => AirType::ClassC,
+        "D" => AirType::ClassD,
+        "E" => AirType::ClassE,
+        "F" => AirType::ClassF,
+        "G" => AirType::ClassG,
+        "P" => AirType::Prohibited,
+        "Q" => AirType::Danger,
+        "R" => AirType::Restricted,
+        "W" => AirType::Gliding,
+        "CTA" => AirType::Cta,
+        "CTR" => AirType::Ctr,
+        "MATZ" => AirType::Matz,
+        "RMZ" => AirType::Rmz,
+        "TMZ" => AirType::Tmz,
+        _ => AirType::Other,
+    }
+}
+
+// OpenAir pen (width, RGB) colour for airspace type
+fn openair_colour(air_type: AirType) -> (u8, (u8, u8, u8)) {
+    match air_type {
+        AirType::Awy | AirType::ClassA => (2, (255, 0, 0)),
+        AirType::ClassB | AirType::ClassC | AirType::ClassD => (2, (0, 0, 255)),
+        AirType::Cta | AirType::Ctr => (2, (0, 0, 255)),
+        AirType::ClassE | AirType::ClassF | AirType::ClassG => (1, (0, 128, 0)),
+        AirType::Prohibited | AirType::Restricted => (2, (192, 0, 0)),
+        AirType::Danger => (2, (255, 128, 0)),
+        AirType::Gliding => (1, (0, 160, 160)),
+        AirType::Matz => (1, (128, 0, 128)),
+        AirType::Rmz | AirType::Tmz => (1, (96, 96, 96)),
+        AirType::Other => (1, (0, 0, 0)),
     }
 }
 
 // Normalise all levels to flight level
 fn norm_level(value: &str) -> u16 {
-    if let Some(fl) = value.strip_prefix("FL") {
-        fl.parse().unwrap()
-    } else if value.ends_with(" ft") {
-        value.split(' ').next().unwrap().parse::<u16>().unwrap() / 100
-    } else {
-        0
-    }
+    parse_altitude(value)
+        .map(|alt| alt.flight_level())
+        .unwrap_or_default()
 }
 
 // Openair level format
 fn format_level(level: &str) -> String {
-    if let Some(alt) = level.strip_suffix(" ft") {
-        // Altitude
-        alt.to_string() + " ft"
-    } else {
-        // Flight level or SFC
-        level.to_string()
+    match parse_altitude(level) {
+        Ok(alt) if alt.is_surface() => "SFC".to_string(),
+        Ok(alt) if alt.is_unlimited() => "UNL".to_string(),
+        Ok(Altitude {
+            value,
+            unit: Unit::FlightLevel,
+            ..
+        }) => format!("FL{}", value),
+        Ok(Altitude {
+            value,
+            reference: Reference::Agl,
+            ..
+        }) => format!("{} ft AGL", value),
+        Ok(Altitude { value, .. }) => format!("{} ft", value),
+        Err(_) => level.to_string(),
     }
 }
 
@@ -132,6 +221,44 @@ fn format_latlon(latlon: &str) -> String {
     )
 }
 
+// Openair lat/lon format from decimal degrees
+fn format_dms((lat, lon): LatLon) -> String {
+    let dms = |value: f64| {
+        let secs = (value.abs() * 3600.0).round() as u32;
+        (secs / 3600, secs / 60 % 60, secs % 60)
+    };
+    let (lat_d, lat_m, lat_s) = dms(lat);
+    let (lon_d, lon_m, lon_s) = dms(lon);
+
+    format!(
+        "{:02}:{:02}:{:02} {} {:03}:{:02}:{:02} {}",
+        lat_d,
+        lat_m,
+        lat_s,
+        if lat < 0.0 { 'S' } else { 'N' },
+        lon_d,
+        lon_m,
+        lon_s,
+        if lon < 0.0 { 'W' } else { 'E' }
+    )
+}
+
+// Openair level with preferred altitude reference notation. There is no
+// terrain data to convert between references, and the source gives
+// ground-relative levels only as SFC, so the preference just makes the
+// reference explicit: SFC becomes 0 ft AGL or altitudes are marked AMSL
+fn format_level_ref(level: &str, altitude_ref: AltitudeRef) -> String {
+    match (altitude_ref, parse_altitude(level)) {
+        (AltitudeRef::PreferAgl, Ok(alt)) if alt.is_surface() => "0 ft AGL".to_string(),
+        (AltitudeRef::PreferAmsl, Ok(alt))
+            if alt.unit == Unit::Feet && alt.reference == Reference::Amsl =>
+        {
+            format_level(level) + " AMSL"
+        }
+        _ => format_level(level),
+    }
+}
+
 // Openair distance format
 fn format_distance(distance: &str) -> String {
     match distance.split_once(' ') {
@@ -145,11 +272,21 @@ fn format_distance(distance: &str) -> String {
         _ => "".to_string(),
     }
 }
+// Escape XML special characters
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 // Remove unwanted feature/volume
 fn airfilter(feature: &Feature, vol: &Volume, settings: &Settings) -> bool {
     let exclude = match feature.local_type {
         // No-ATZ
         Some(LocalType::NoAtz) => settings.unlicensed.is_none(),
+        // Military ATZ
+        Some(LocalType::Matz) => settings.matz.is_none(),
         // Microlight
         Some(LocalType::Ul) => settings.microlight.is_none(),
         // Gliding airspace
@@ -165,8 +302,11 @@ fn airfilter(feature: &Feature, vol: &Volume, settings: &Settings) -> bool {
 
                 !settings.wave.contains(&feature.name) && !rules.contains(&Rule::Loa)
             } else {
-                // Gliding Site
-                settings.gliding.is_none() || settings.home.as_ref() == Some(&feature.name)
+                // Gliding Site, optionally limited to selected sites
+                settings.gliding.is_none()
+                    || settings.home.as_ref() == Some(&feature.name)
+                    || !(settings.gliding_sites.is_empty()
+                        || settings.gliding_sites.contains(&feature.name))
             }
         }
         // HIRTA/GVS/Laser
@@ -176,12 +316,121 @@ fn airfilter(feature: &Feature, vol: &Volume, settings: &Settings) -> bool {
         _ => false,
     };
 
-    !(exclude || (norm_level(&vol.lower) >= settings.max_level))
+    let air_type = class_airtype(volume_type(feature, vol, settings));
+    let disabled = settings.types.get(&air_type) == Some(&false);
+
+    // Per type cap can only lower the maximum level
+    let capped = settings
+        .type_caps
+        .get(&air_type)
+        .is_some_and(|cap| norm_level(&vol.lower) >= *cap);
+
+    // Wave box entirely below the wave band, or dropped because the
+    // maximum level cuts through it
+    let below_wave = is_wave(feature, settings)
+        && (settings
+            .wave_min_fl
+            .is_some_and(|level| norm_level(&vol.upper) <= level)
+            || (settings.wave_clip == WaveClip::Drop
+                && norm_level(&vol.upper) > settings.max_level));
+
+    // Danger area flagged as inactive or disused
+    let inactive = settings.exclude_inactive_danger
+        && air_type == AirType::Danger
+        && feature
+            .rules
+            .iter()
+            .chain(vol.rules.iter())
+            .flatten()
+            .any(|rule| *rule == Rule::Inactive);
+
+    // Volume without a listening frequency
+    let no_frequency = settings.require_frequency && vol.frequency.is_none();
+
+    !(exclude
+        || disabled
+        || capped
+        || below_wave
+        || inactive
+        || no_frequency
+        || !in_band(vol, settings)
+        || settings.exclude.contains(&feature.name))
+}
+
+// Selected wave box
+fn is_wave(feature: &Feature, settings: &Settings) -> bool {
+    feature.local_type == Some(LocalType::Glider)
+        && feature.icao_type == IcaoType::DOther
+        && settings.wave.contains(&feature.name)
+}
+
+// Wave box volume clipped to the minimum wave level and, unless kept or
+// dropped, the maximum level. None if no clipping is needed
+fn clip_wave(feature: &Feature, vol: &Volume, settings: &Settings) -> Option<Volume> {
+    if !is_wave(feature, settings) {
+        return None;
+    }
+
+    let mut clipped = vol.clone();
+    let mut changed = false;
+    if let Some(min_fl) = settings.wave_min_fl {
+        if norm_level(&vol.lower) < min_fl {
+            clipped.lower = format!("FL{}", min_fl);
+            changed = true;
+        }
+    }
+    if settings.wave_clip == WaveClip::Clip && norm_level(&vol.upper) > settings.max_level {
+        clipped.upper = format!("FL{}", settings.max_level);
+        changed = true;
+    }
+    changed.then_some(clipped)
+}
+
+// Check volume is within selected altitude band
+fn in_band(vol: &Volume, settings: &Settings) -> bool {
+    let lower = norm_level(&vol.lower);
+
+    lower < settings.max_level
+        && norm_level(&vol.upper) >= settings.min_level
+        && !(settings.low_only && lower > LOW_ONLY_LEVEL)
+}
+
+// Volumes of a feature remaining after filtering, with volume index.
+// Selected wave boxes are clipped to the wave levels
+fn feature_volumes<'a>(
+    feature: &'a Feature,
+    settings: &'a Settings,
+) -> impl Iterator<Item = (&'a Feature, usize, Cow<'a, Volume>)> + 'a {
+    feature
+        .geometry
+        .iter()
+        .enumerate()
+        .filter(move |(_, volume)| airfilter(feature, volume, settings))
+        .map(move |(n, volume)| {
+            let clipped = clip_wave(feature, volume, settings);
+            (
+                feature,
+                n,
+                clipped.map_or(Cow::Borrowed(volume), Cow::Owned),
+            )
+        })
+}
+
+// Feature volumes remaining after filtering, with volume index. Stacked
+// layers are separate volumes in the source so each layer is always
+// written as its own block
+fn filtered_volumes<'a>(
+    airspace: &'a [Feature],
+    settings: &'a Settings,
+) -> impl Iterator<Item = (&'a Feature, usize, Cow<'a, Volume>)> + 'a {
+    airspace
+        .iter()
+        .flat_map(move |feature| feature_volumes(feature, settings))
 }
 
 // Give each volume a name
-fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> String {
-    let name = if let Some(name) = &vol.name {
+fn volume_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> String {
+    if let Some(name) = &vol.name {
         name.clone()
     } else {
         let mut name = feature.name.clone();
@@ -215,7 +464,7 @@ fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> St
         if settings.format == Format::Competition && feature.geometry.len() > 1 {
             name.push('-');
             if let Some(seq) = &vol.seq {
-                name += &seq;
+                name += seq;
             } else {
                 let x = (b'A'..=b'Z').map(|c| c as char).nth(n);
                 name.push(x.unwrap());
@@ -244,13 +493,28 @@ fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> St
         };
 
         name
-    };
+    }
+}
+
+fn do_name(feature: &Feature, vol: &Volume, n: usize, settings: &Settings) -> String {
+    match &settings.name_prefix {
+        Some(prefix) => format!("AN {} {}\n", prefix, volume_name(feature, vol, n, settings)),
+        None => format!("AN {}\n", volume_name(feature, vol, n, settings)),
+    }
+}
 
-    format!("AN {}\n", name)
+// NOTAM activated airspace
+fn is_notam(feature: &Feature, volume: &Volume) -> bool {
+    feature
+        .rules
+        .iter()
+        .chain(volume.rules.iter())
+        .flatten()
+        .any(|rule| *rule == Rule::Notam)
 }
 
 // Give each volume a type
-fn do_type(feature: &Feature, volume: &Volume, settings: &Settings) -> String {
+fn volume_type(feature: &Feature, volume: &Volume, settings: &Settings) -> &'static str {
     let rules = feature
         .rules
         .iter()
@@ -260,12 +524,12 @@ fn do_type(feature: &Feature, volume: &Volume, settings: &Settings) -> String {
 
     let comp = settings.format == Format::Competition;
 
-    let openair_type = if rules.contains(&Rule::Notam) {
+    if is_notam(feature, volume) {
         // NOTAM activated airspace
         "G"
     } else {
         match feature.icao_type {
-            IcaoType::Atz => settings.atz.as_str(),
+            IcaoType::Atz => openair_class(settings.atz),
             IcaoType::D => {
                 if comp && rules.contains(&Rule::Si) {
                     // Danger area with SI
@@ -285,11 +549,11 @@ fn do_type(feature: &Feature, volume: &Volume, settings: &Settings) -> String {
                 } else {
                     match feature.local_type {
                         Some(LocalType::Hirta) | Some(LocalType::Gvs) | Some(LocalType::Laser) => {
-                            settings.hirta_gvs.unwrap_or(AirType::Other).as_str()
+                            openair_class(settings.hirta_gvs.unwrap_or(AirType::Other))
                         }
                         Some(LocalType::Glider) => "W",
                         Some(LocalType::Obstacle) => {
-                            settings.obstacle.unwrap_or(AirType::Other).as_str()
+                            openair_class(settings.obstacle.unwrap_or(AirType::Other))
                         }
                         _ => "Q",
                     }
@@ -300,15 +564,17 @@ fn do_type(feature: &Feature, volume: &Volume, settings: &Settings) -> String {
                     if rules.contains(&Rule::Loa) {
                         "W"
                     } else {
-                        settings.gliding.unwrap_or(AirType::Other).as_str()
+                        openair_class(settings.gliding.unwrap_or(AirType::Other))
                     }
                 }
-                Some(LocalType::Ils) => settings.ils.unwrap_or(settings.atz).as_str(),
-                Some(LocalType::Matz) => "MATZ",
-                Some(LocalType::NoAtz) => settings.unlicensed.unwrap_or(AirType::Other).as_str(),
+                Some(LocalType::Ils) => openair_class(settings.ils.unwrap_or(settings.atz)),
+                Some(LocalType::Matz) => openair_class(settings.matz.unwrap_or(AirType::Matz)),
+                Some(LocalType::NoAtz) => {
+                    openair_class(settings.unlicensed.unwrap_or(AirType::Other))
+                }
                 Some(LocalType::Rat) => "P",
                 Some(LocalType::Tmz) => "TMZ",
-                Some(LocalType::Ul) => settings.microlight.unwrap_or(AirType::Other).as_str(),
+                Some(LocalType::Ul) => openair_class(settings.microlight.unwrap_or(AirType::Other)),
                 Some(LocalType::Rmz) => "RMZ",
                 _ => "OTHER",
             },
@@ -328,21 +594,74 @@ fn do_type(feature: &Feature, volume: &Volume, settings: &Settings) -> String {
                 }
             }
         }
-    };
+    }
+}
 
-    format!("AC {}\n", openair_type)
+fn do_type(feature: &Feature, volume: &Volume, settings: &Settings) -> String {
+    format!("AC {}\n", volume_type(feature, volume, settings))
+}
+
+fn do_colour(class: &str) -> String {
+    let (width, (r, g, b)) = openair_colour(class_airtype(class));
+    format!("SP 0,{},{},{},{}\nSB -1,-1,-1\n", width, r, g, b)
 }
 
-fn do_levels(volume: &Volume) -> String {
+fn do_levels(volume: &Volume, settings: &Settings) -> String {
     format!(
         "AL {}\nAH {}\n",
-        format_level(&volume.lower),
-        format_level(&volume.upper)
+        format_level_ref(&volume.lower, settings.altitude_ref),
+        format_level_ref(&volume.upper, settings.altitude_ref)
     )
 }
 
-fn do_freq(freq: f64) -> String {
-    format!("AF {:.3}\n", freq)
+// Frequency with two or three decimal places. 8.33 kHz channels are
+// truncated, not rounded, to two places, e.g. 118.105 to 118.10
+fn format_freq(freq: f64, decimals: u8) -> String {
+    let mut freq = format!("{:.3}", freq);
+    if decimals == 2 {
+        freq.pop();
+    }
+    freq
+}
+
+fn do_freq(freq: f64, settings: &Settings) -> String {
+    format!("AF {}\n", format_freq(freq, settings.freq_decimals))
+}
+
+fn do_freq_comment(freq: f64, callsign: Option<&str>, settings: &Settings) -> String {
+    let freq = format_freq(freq, settings.freq_decimals);
+    match callsign {
+        Some(callsign) => format!("* {} {}\n", freq, callsign),
+        None => format!("* {}\n", freq),
+    }
+}
+
+// Source notes and rules as comment lines
+fn do_notes(feature: &Feature, vol: &Volume) -> String {
+    let mut out = String::new();
+    if let Some(notes) = &feature.notes {
+        for line in textwrap::wrap(notes, 72) {
+            out.push_str(&format!("* {}\n", line));
+        }
+    }
+
+    let mut rules = feature
+        .rules
+        .iter()
+        .chain(vol.rules.iter())
+        .flatten()
+        .map(|rule| rule.as_str())
+        .collect::<Vec<&str>>();
+    rules.sort();
+    rules.dedup();
+    if !rules.is_empty() {
+        out.push_str(&format!("* Rules: {}\n", rules.join(", ")));
+    }
+    out
+}
+
+fn do_callsign(callsign: &str) -> String {
+    format!("AG {}\n", callsign)
 }
 
 fn do_point(point: &str) -> String {
@@ -406,11 +725,11 @@ fn do_boundary(boundary: &[Boundary]) -> String {
 
 // Merge radio frequency data
 fn merge_services(airspace: &mut Vec<Feature>, services: &Vec<Service>) {
-    // Create frequency map
+    // Create map of frequency and callsign
     let mut frequencies = HashMap::new();
     for service in services {
         for id in &service.controls {
-            frequencies.insert(id, service.frequency);
+            frequencies.insert(id, (service.frequency, &service.callsign));
         }
     }
 
@@ -429,7 +748,10 @@ fn merge_services(airspace: &mut Vec<Feature>, services: &Vec<Service>) {
                 None
             };
 
-            volume.frequency = volume_freq.or(feature_freq).cloned();
+            if let Some((freq, callsign)) = volume_freq.or(feature_freq) {
+                volume.frequency = Some(*freq);
+                volume.callsign = Some(callsign.to_string());
+            }
         }
     }
 }
@@ -491,8 +813,16 @@ fn merge_loa(airspace: &mut Vec<Feature>, loas: &Vec<&Loa>) {
     }
 }
 
-fn add_obstacles(airspace: &mut Vec<Feature>, obstacles: &Vec<Obstacle>) {
+fn add_obstacles(airspace: &mut Vec<Feature>, obstacles: &Vec<Obstacle>, min_ft: Option<u16>) {
     for obstacle in obstacles {
+        // Skip obstacles below minimum height, e.g. "450 ft"
+        let elevation = parse_altitude(&obstacle.elevation).map(|alt| alt.feet());
+        if let (Some(min_ft), Ok(elevation)) = (min_ft, elevation) {
+            if elevation < min_ft as u32 {
+                continue;
+            }
+        }
+
         let feature = Feature {
             name: obstacle.name.clone(),
             icao_type: IcaoType::DOther,
@@ -500,6 +830,9 @@ fn add_obstacles(airspace: &mut Vec<Feature>, obstacles: &Vec<Obstacle>) {
             id: None,
             local_type: Some(LocalType::Obstacle),
             rules: None,
+            date: None,
+            hours: None,
+            notes: None,
             geometry: vec![Volume {
                 upper: obstacle.elevation.clone(),
                 lower: "SFC".to_string(),
@@ -509,6 +842,7 @@ fn add_obstacles(airspace: &mut Vec<Feature>, obstacles: &Vec<Obstacle>) {
                 })],
                 icao_class: None,
                 frequency: None,
+                callsign: None,
                 id: None,
                 name: None,
                 rules: None,
@@ -519,9 +853,27 @@ fn add_obstacles(airspace: &mut Vec<Feature>, obstacles: &Vec<Obstacle>) {
     }
 }
 
-// File header
-fn header(note: &str, airac: &str, commit: &str, user_agent: &str, settings: &Settings) -> String {
-    let mut hdr = "UK Airspace\n\
+// File header, with each line prefixed by the comment marker
+fn header(
+    note: &str,
+    airac: &str,
+    commit: &str,
+    user_agent: &str,
+    settings: &Settings,
+    comment: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let mut hdr = String::new();
+    if let Some(author) = &settings.author {
+        hdr.push_str(&format!(
+            "Generated by asselect for {}\n{}\n\n",
+            author,
+            now.to_rfc3339()
+        ));
+    }
+
+    hdr.push_str(
+        "UK Airspace\n\
         Alan Sparrow (airspace@asselect.uk)\n\
         \n\
         I have tried to make this data as accurate as possible but\n\
@@ -533,23 +885,23 @@ fn header(note: &str, airac: &str, commit: &str, user_agent: &str, settings: &Se
         in this file is based on the work of others including: George Knight,\n\
         Geoff Brown, Peter Desmond and Rory O'Connor.  The data is originally\n\
         sourced from the UK Aeronautical Information Package (AIP).\n\
-        \n"
-    .to_string();
+        \n",
+    );
 
     hdr.push_str(note);
     hdr.push_str(&format!("\nAIRAC: {}\n", &airac[..10]));
     hdr.push_str(&format!("Commit: {}\n", commit));
-    hdr.push_str(&format!("Produced: {}\n", Utc::now().to_rfc3339()));
+    hdr.push_str(&format!("Produced: {}\n", now.to_rfc3339()));
     hdr.push_str(&format!("User agent: {}\n", user_agent));
-    hdr.push_str(&textwrap::fill(format!("{:?}", settings).as_str(), 72));
+    hdr.push_str(&textwrap::fill(&settings.to_full_query_string(), 72));
 
-    // Prepend "*" to lines
+    // Prepend comment marker to lines
     hdr.split('\n')
         .map(|x| {
             if x.is_empty() {
-                "*".to_string()
+                comment.to_string()
             } else {
-                "* ".to_owned() + x
+                format!("{} {}", comment, x)
             }
         })
         .collect::<Vec<String>>()
@@ -557,17 +909,221 @@ fn header(note: &str, airac: &str, commit: &str, user_agent: &str, settings: &Se
         + "\n"
 }
 
-// Generate OpenAir data
-pub fn openair(yaixm: &Yaixm, settings: &Settings, user_agent: &str) -> String {
+// Levenshtein edit distance
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut row = (0..=b.len()).collect::<Vec<usize>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let subst = diag + usize::from(ca != *cb);
+            diag = row[j + 1];
+            row[j + 1] = subst.min(row[j] + 1).min(diag + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// Find home airfield by name, ignoring case and surrounding space, falling
+// back to the closest name within a small edit distance
+fn resolve_home<'a>(name: &str, catalog: &[&'a Feature]) -> Option<&'a Feature> {
+    let name = name.trim().to_lowercase();
+
+    catalog
+        .iter()
+        .find(|site| site.name.trim().to_lowercase() == name)
+        .or_else(|| {
+            catalog
+                .iter()
+                .map(|site| (edit_distance(&name, &site.name.trim().to_lowercase()), site))
+                .filter(|(distance, _)| *distance <= HOME_MATCH_DISTANCE)
+                .min_by_key(|(distance, _)| *distance)
+                .map(|(_, site)| site)
+        })
+        .copied()
+}
+
+// Home position and radius (nm) limiting the output area. Explicit
+// coordinates take precedence over the home airfield
+fn home_area(yaixm: &Yaixm, settings: &Settings) -> Option<(LatLon, f64)> {
+    let radius = settings.home_radius_nm?;
+    if let Some(coords) = settings.home_coords {
+        return Some((coords, radius as f64));
+    }
+
+    let home = settings.home.as_ref()?;
+    site_centre(yaixm, home).map(|centre| (centre, radius as f64))
+}
+
+// Competition task area centre and radius (nm), Competition format only
+fn competition_area(yaixm: &Yaixm, settings: &Settings) -> Option<(LatLon, f64)> {
+    if settings.format != Format::Competition {
+        return None;
+    }
+
+    let radius = settings.competition_radius_nm?;
+    let centre = settings.competition_center.as_ref()?;
+    site_centre(yaixm, centre).map(|centre| (centre, radius as f64))
+}
+
+// Centre of named (or nearly named) gliding site
+fn site_centre(yaixm: &Yaixm, name: &str) -> Option<LatLon> {
+    let sites = yaixm
+        .airspace
+        .iter()
+        .filter(|x| x.icao_type == IcaoType::Other && x.local_type == Some(LocalType::Glider))
+        .collect::<Vec<&Feature>>();
+    let site = resolve_home(name, &sites)?;
+
+    site.geometry
+        .iter()
+        .flat_map(|vol| vol.boundary.iter())
+        .find_map(|b| match b {
+            Boundary::Circle(circle) => Some(parse_latlon(&circle.centre)),
+            _ => None,
+        })
+}
+
+// Remove volumes outside the area of interest
+fn clip_area(airspace: &mut Vec<Feature>, inside: impl Fn(&[LatLon]) -> bool) {
+    for feature in airspace.iter_mut() {
+        feature
+            .geometry
+            .retain(|vol| inside(&boundary_ring(&vol.boundary, ARC_RESOLUTION)));
+    }
+    airspace.retain(|feature| !feature.geometry.is_empty());
+}
+
+// RAT selected and, if it has an effective date, not older than cut-off
+fn rat_selected(rat: &Feature, settings: &Settings) -> bool {
+    if !settings.rat.contains(&rat.name) {
+        return false;
+    }
+
+    let from = settings.rat_from.as_deref().and_then(parse_date);
+    let date = rat.date.as_deref().and_then(parse_date);
+    match (from, date) {
+        (Some(from), Some(date)) => date >= from,
+        _ => true,
+    }
+}
+
+// Selected RA(T)s, in selection order
+fn selected_rats(yaixm: &Yaixm, settings: &Settings) -> Vec<Feature> {
+    settings
+        .rat
+        .iter()
+        .filter_map(|name| yaixm.rat.iter().find(|rat| rat.name == *name))
+        .filter(|rat| rat_selected(rat, settings))
+        .cloned()
+        .collect()
+}
+
+// Minutes since midnight from HHMM
+fn parse_hhmm(value: &str) -> Option<u32> {
+    if value.len() != 4 {
+        return None;
+    }
+    let hours = value.get(..2)?.parse::<u32>().ok()?;
+    let minutes = value.get(2..)?.parse::<u32>().ok()?;
+
+    (hours <= 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+// Check feature isn't definitely inactive at the given time. Active hours
+// are "HHMM-HHMM" (UTC), possibly spanning midnight. Features without
+// (or with unreadable) hours are assumed active
+fn active_at(feature: &Feature, now: DateTime<Utc>) -> bool {
+    let hours = match &feature.hours {
+        Some(hours) => hours,
+        None => return true,
+    };
+
+    let (start, end) = match hours.trim().split_once('-') {
+        Some((start, end)) => match (parse_hhmm(start.trim()), parse_hhmm(end.trim())) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return true,
+        },
+        None => return true,
+    };
+
+    let minutes = now.hour() * 60 + now.minute();
+    if start <= end {
+        minutes >= start && minutes < end
+    } else {
+        minutes >= start || minutes < end
+    }
+}
+
+// Remove features inactive at the given time
+fn active_filter(airspace: &mut Vec<Feature>, now: DateTime<Utc>) {
+    airspace.retain(|feature| active_at(feature, now));
+}
+
+// Build list of airspace features, prior to filtering
+fn build_airspace(yaixm: &Yaixm, settings: &Settings, now: DateTime<Utc>) -> Vec<Feature> {
+    select_airspace(yaixm, settings, now).0
+}
+
+// Drop highest airspace to keep within the feature limit, returns true if
+// any airspace was dropped
+fn limit_features(airspace: &mut Vec<Feature>, settings: &Settings) -> bool {
+    let Some(max_features) = settings.max_features else {
+        return false;
+    };
+
+    airspace.retain(|feature| output_feature(feature, settings));
+    if airspace.len() <= max_features {
+        return false;
+    }
+
+    let lowest = |feature: &Feature| {
+        feature
+            .geometry
+            .iter()
+            .filter(|vol| airfilter(feature, vol, settings))
+            .map(|vol| norm_level(&vol.lower))
+            .min()
+            .unwrap_or(u16::MAX)
+    };
+    airspace.sort_by_cached_key(lowest);
+    airspace.truncate(max_features);
+    true
+}
+
+// Increase radius of circular ATZs by buffer (nm)
+fn buffer_atz(airspace: &mut [Feature], buffer: f64) {
+    for feature in airspace
+        .iter_mut()
+        .filter(|feature| feature.icao_type == IcaoType::Atz)
+    {
+        for vol in feature.geometry.iter_mut() {
+            for segment in vol.boundary.iter_mut() {
+                if let Boundary::Circle(circle) = segment {
+                    circle.radius = format!("{} nm", parse_distance(&circle.radius) + buffer);
+                }
+            }
+        }
+    }
+}
+
+// Feature has at least one volume for output
+fn output_feature(feature: &Feature, settings: &Settings) -> bool {
+    feature
+        .geometry
+        .iter()
+        .any(|vol| airfilter(feature, vol, settings))
+}
+
+// Airspace features for output at the given time, with feature limit
+// truncation flag
+fn select_airspace(yaixm: &Yaixm, settings: &Settings, now: DateTime<Utc>) -> (Vec<Feature>, bool) {
     let mut airspace = yaixm.airspace.clone();
 
     if settings.format == Format::RatOnly {
-        airspace = yaixm
-            .rat
-            .clone()
-            .into_iter()
-            .filter(|rat| settings.rat.contains(&rat.name))
-            .collect();
+        airspace = selected_rats(yaixm, settings);
     } else {
         // Merge LOAs
         let loas = yaixm
@@ -577,25 +1133,113 @@ pub fn openair(yaixm: &Yaixm, settings: &Settings, user_agent: &str) -> String {
             .collect::<Vec<&Loa>>();
         merge_loa(&mut airspace, &loas);
 
+        // Widen circular ATZs
+        if let Some(buffer) = settings.atz_buffer_nm {
+            buffer_atz(&mut airspace, buffer);
+        }
+
         // Add obstacles
         if settings.obstacle.is_some() {
-            add_obstacles(&mut airspace, &yaixm.obstacle);
+            add_obstacles(&mut airspace, &yaixm.obstacle, settings.obstacle_min_ft);
         }
 
         // Append RA(T)s
-        airspace.append(
-            &mut yaixm
-                .rat
-                .iter()
-                .filter(|rat| settings.rat.contains(&rat.name))
-                .cloned()
-                .collect::<Vec<Feature>>(),
-        );
+        airspace.append(&mut selected_rats(yaixm, settings));
 
         // Merge radio frequencies
         merge_services(&mut airspace, &yaixm.service);
     }
 
+    // Limit to area around home airfield
+    if let Some((centre, radius)) = home_area(yaixm, settings) {
+        clip_area(&mut airspace, |ring| {
+            intersects_circle(ring, centre, radius)
+        });
+    }
+
+    // Limit to competition task area
+    if let Some((centre, radius)) = competition_area(yaixm, settings) {
+        clip_area(&mut airspace, |ring| {
+            intersects_circle(ring, centre, radius)
+        });
+    }
+
+    // Remove currently inactive airspace
+    if settings.active_now {
+        active_filter(&mut airspace, now);
+    }
+
+    // Limit to bounding box
+    if let Some(bbox) = settings.bbox {
+        clip_area(&mut airspace, |ring| intersects_box(ring, bbox));
+    }
+
+    // Keep lowest airspace if there is too much
+    let truncated = limit_features(&mut airspace, settings);
+
+    // Stable output order, independent of selection order, except RA(T)s
+    // which follow the rest of the airspace in selection order
+    let rat_index = |feature: &Feature| {
+        (feature.local_type == Some(LocalType::Rat))
+            .then(|| settings.rat.iter().position(|rat| *rat == feature.name))
+            .flatten()
+    };
+    airspace.sort_by(|a, b| {
+        rat_index(a)
+            .cmp(&rat_index(b))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    (airspace, truncated)
+}
+
+// Generate overlay showing base of controlled airspace below the given
+// flight level, encoded as Class B
+pub fn custom_overlay(yaixm: &Yaixm, level: u16) -> String {
+    let mut output = String::new();
+    for feature in &yaixm.airspace {
+        if !matches!(
+            feature.icao_type,
+            IcaoType::Cta | IcaoType::Ctr | IcaoType::Tma
+        ) {
+            continue;
+        }
+
+        for volume in &feature.geometry {
+            let base = norm_level(&volume.lower);
+            if base > 0 && base < level {
+                output.push_str("*\n");
+                output.push_str("AC B\n");
+                output.push_str(&format!("AN {}\n", format_level(&volume.lower)));
+                output.push_str(&format!(
+                    "AL {}\nAH {}\n",
+                    format_level(&volume.lower),
+                    format_level(&volume.lower)
+                ));
+                output.push_str(&do_boundary(&volume.boundary));
+            }
+        }
+    }
+    output
+}
+
+// LK8000 activity, from active hours or NOTAM activation
+fn activity(feature: &Feature, volume: &Volume) -> Option<String> {
+    match &feature.hours {
+        Some(hours) => Some(hours.trim().to_string()),
+        None if is_notam(feature, volume) => Some("NOTAM".to_string()),
+        None => None,
+    }
+}
+
+// Generate OpenAir data, with header timestamp
+pub fn openair<'a>(
+    yaixm: &Yaixm,
+    volumes: impl IntoIterator<Item = (&'a Feature, usize, Cow<'a, Volume>)>,
+    settings: &Settings,
+    user_agent: &str,
+    now: DateTime<Utc>,
+) -> String {
     // Build OpenAir data
     let rel = &yaixm.release;
     let mut output = header(
@@ -604,20 +1248,1256 @@ pub fn openair(yaixm: &Yaixm, settings: &Settings, user_agent: &str) -> String {
         &rel.commit,
         user_agent,
         settings,
+        "*",
+        now,
     );
-    for feature in airspace {
-        for (n, volume) in feature.geometry.iter().enumerate() {
-            if airfilter(&feature, volume, settings) {
-                output.push_str("*\n");
-                output.push_str(&do_type(&feature, volume, settings));
-                output.push_str(&do_name(&feature, volume, n, settings));
-                if let Some(freq) = volume.frequency {
-                    output.push_str(&do_freq(freq));
-                }
-                output.push_str(&do_levels(volume));
-                output.push_str(&do_boundary(&volume.boundary));
+    output.push_str(&openair_volumes(volumes, settings));
+    output
+}
+
+// OpenAir records for a list of feature volumes
+fn openair_volumes<'a>(
+    volumes: impl IntoIterator<Item = (&'a Feature, usize, Cow<'a, Volume>)>,
+    settings: &Settings,
+) -> String {
+    let lk = settings.format == Format::OpenAirLk;
+
+    let mut output = String::new();
+    let mut names = HashMap::new();
+    for (feature, n, volume) in volumes {
+        output.push_str("*\n");
+        if settings.include_notes {
+            output.push_str(&do_notes(feature, &volume));
+        }
+        output.push_str(&do_type(feature, &volume, settings));
+        if lk {
+            // LK8000 colours follow the class, and are always included
+            output.push_str(&do_colour(volume_type(feature, &volume, settings)));
+            if let Some(activity) = activity(feature, &volume) {
+                output.push_str(&format!("AY {}\n", activity));
+            }
+        }
+        let mut name = do_name(feature, &volume, n, settings);
+        if settings.dedupe_names {
+            // Number repeated names, (2), (3) etc.
+            let count = names.entry(name.clone()).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                name = format!("{} ({})\n", name.trim_end(), count);
+            }
+        }
+        output.push_str(&name);
+        if settings.colorize && !lk {
+            output.push_str(&do_colour(volume_type(feature, &volume, settings)));
+        }
+        if settings.radio {
+            if let Some(freq) = volume.frequency {
+                output.push_str(&do_freq(freq, settings));
+            }
+            if let Some(callsign) = &volume.callsign {
+                output.push_str(&do_callsign(callsign));
+            }
+        } else if settings.radio_as_comment {
+            if let Some(freq) = volume.frequency {
+                output.push_str(&do_freq_comment(freq, volume.callsign.as_deref(), settings));
+            }
+        }
+        output.push_str(&do_levels(&volume, settings));
+        if settings.label_points {
+            let ring = boundary_ring(&volume.boundary, settings.arc_resolution);
+            if let Some(point) = centroid(&ring) {
+                output.push_str(&format!("AT {}\n", format_dms(point)));
             }
         }
+        output.push_str(&do_boundary(&volume.boundary));
+    }
+    output
+}
+
+// Convert output to Windows (CRLF) line endings if required
+pub fn line_endings(text: &str, settings: &Settings) -> String {
+    let text = text.replace("\r\n", "\n");
+    if settings.crlf {
+        text.replace('\n', "\r\n")
+    } else {
+        text
+    }
+}
+
+// Check selected airspace is complete enough to be written
+fn validate(airspace: &[Feature], settings: &Settings) -> Result<(), WriterError> {
+    if let Some(feature) = airspace
+        .iter()
+        .find(|feature| feature.geometry.is_empty() && !settings.exclude.contains(&feature.name))
+    {
+        return Err(WriterError::MissingGeometry(feature.name.clone()));
+    }
+
+    for (feature, _, volume) in filtered_volumes(airspace, settings) {
+        if volume.boundary.is_empty() {
+            return Err(WriterError::MissingGeometry(feature.name.clone()));
+        }
+
+        for level in [&volume.lower, &volume.upper] {
+            if parse_altitude(level).is_err() {
+                return Err(WriterError::BadAltitude {
+                    name: feature.name.clone(),
+                    level: level.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+// Format is OpenAir, or a variant of it
+pub fn is_openair(format: Format) -> bool {
+    matches!(
+        format,
+        Format::OpenAir | Format::RatOnly | Format::Competition | Format::OpenAirLk
+    )
+}
+
+// Filename extension for output format
+pub fn extension(format: Format) -> &'static str {
+    match format {
+        Format::Tnp => "sua",
+        Format::GeoJson => "geojson",
+        Format::Gpx => "gpx",
+        Format::Cup => "cup",
+        Format::Kml => "kml",
+        Format::Csv => "csv",
+        _ => "txt",
+    }
+}
+
+// Generated airspace data, with number of features and whether the feature
+// limit was exceeded
+pub struct Generated {
+    pub data: String,
+    pub count: usize,
+    pub truncated: bool,
+}
+
+// Generate airspace data in the selected format, with header timestamp
+pub fn generate(
+    yaixm: &Yaixm,
+    settings: &Settings,
+    user_agent: &str,
+    now: DateTime<Utc>,
+) -> Result<Generated, WriterError> {
+    generate_with_progress(yaixm, settings, user_agent, now, |_| ())
+}
+
+// Generate airspace data, reporting fractional completion (0.0 to 1.0)
+// as each feature is written
+pub fn generate_with_progress(
+    yaixm: &Yaixm,
+    settings: &Settings,
+    user_agent: &str,
+    now: DateTime<Utc>,
+    mut on_progress: impl FnMut(f32),
+) -> Result<Generated, WriterError> {
+    let (airspace, truncated) = select_airspace(yaixm, settings, now);
+
+    validate(&airspace, settings)?;
+    let count = airspace
+        .iter()
+        .filter(|feature| output_feature(feature, settings))
+        .count();
+
+    // Writers take the filtered volumes lazily, so progress follows the
+    // writer through the airspace
+    let total = airspace.len() as f32;
+    let volumes = airspace.iter().enumerate().flat_map(|(n, feature)| {
+        on_progress(n as f32 / total);
+        feature_volumes(feature, settings)
+    });
+
+    let data = match settings.format {
+        Format::Tnp => tnp::tnp(yaixm, volumes, settings, user_agent, now),
+        Format::GeoJson => geojson::geojson(volumes, settings),
+        Format::Gpx => gpx::gpx(volumes, settings),
+        Format::Cup => cup::cup(volumes),
+        Format::Kml => kml::kml(volumes, settings),
+        Format::Csv => csv::csv(volumes, settings),
+        _ => openair(yaixm, volumes, settings, user_agent, now),
+    };
+    on_progress(1.0);
+
+    Ok(Generated {
+        data,
+        count,
+        truncated,
+    })
+}
+
+// Airspace data in several formats as filename/content pairs, named from
+// the filename stem and format. Overlays are not included
+pub fn generate_bundle(
+    yaixm: &Yaixm,
+    settings: &Settings,
+    formats: &[Format],
+    stem: &str,
+    user_agent: &str,
+    now: DateTime<Utc>,
+) -> Result<Vec<(String, String)>, WriterError> {
+    formats
+        .iter()
+        .map(|format| {
+            let settings = Settings {
+                format: *format,
+                ..settings.clone()
+            };
+            let generated = generate(yaixm, &settings, user_agent, now)?;
+
+            Ok((
+                format!("{}_{}.{}", stem, format_value(*format), extension(*format)),
+                line_endings(&generated.data, &settings),
+            ))
+        })
+        .collect()
+}
+
+// Separate OpenAir data for each airspace type in the selection as
+// filename/content pairs, named from the filename stem and type, with
+// feature limit truncation flag. Overlays are not included
+pub fn generate_split(
+    yaixm: &Yaixm,
+    settings: &Settings,
+    stem: &str,
+    user_agent: &str,
+    now: DateTime<Utc>,
+) -> Result<(Vec<(String, String)>, bool), WriterError> {
+    let settings = Settings {
+        format: if is_openair(settings.format) {
+            settings.format
+        } else {
+            Format::OpenAir
+        },
+        ..settings.clone()
+    };
+
+    let (airspace, truncated) = select_airspace(yaixm, &settings, now);
+    validate(&airspace, &settings)?;
+
+    let air_type =
+        |feature: &Feature, volume: &Volume| class_airtype(volume_type(feature, volume, &settings));
+    let mut present = filtered_volumes(&airspace, &settings)
+        .map(|(feature, _, volume)| air_type(feature, &volume))
+        .collect::<Vec<AirType>>();
+    present.sort();
+    present.dedup();
+
+    let entries = present
+        .into_iter()
+        .map(|split_type| {
+            let volumes = filtered_volumes(&airspace, &settings)
+                .filter(|(feature, _, volume)| air_type(feature, volume) == split_type);
+            let data = openair(yaixm, volumes, &settings, user_agent, now);
+
+            (
+                format!("{}_{}.txt", stem, airtype_value(split_type)),
+                line_endings(&data, &settings),
+            )
+        })
+        .collect();
+    Ok((entries, truncated))
+}
+
+// Quick OpenAir preview of the lowest airspace in the selection, limited to
+// a number of volumes (AC records), with a marker comment if more would be
+// output. Only the previewed volumes are written
+pub fn generate_preview(
+    settings: &Settings,
+    source: &Yaixm,
+    limit: usize,
+    now: DateTime<Utc>,
+) -> String {
+    let settings = Settings {
+        format: if is_openair(settings.format) {
+            settings.format
+        } else {
+            Format::OpenAir
+        },
+        ..settings.clone()
+    };
+
+    let (airspace, _) = select_airspace(source, &settings, now);
+    let mut volumes = filtered_volumes(&airspace, &settings).collect::<Vec<_>>();
+    volumes.sort_by_key(|(_, _, volume)| norm_level(&volume.lower));
+    let truncated = volumes.len() > limit;
+    volumes.truncate(limit);
+
+    let mut output = openair_volumes(volumes, &settings);
+    if truncated {
+        output.push_str("* ... truncated\n");
     }
     output
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    // Closed polygon around a centre point, e.g. "512000N 0020000W"
+    fn square(lat: &str, lon: &str) -> serde_json::Value {
+        json!([{"line": [
+            format!("{}00N {}00W", lat, lon),
+            format!("{}00N {}30W", lat, lon),
+            format!("{}30N {}30W", lat, lon),
+            format!("{}30N {}00W", lat, lon),
+        ]}])
+    }
+
+    // Small dataset with one of most kinds of airspace
+    pub(crate) fn fixture() -> Yaixm {
+        serde_json::from_value(json!({
+            "airspace": [
+                {
+                    "id": "bristol-ctr",
+                    "name": "BRISTOL CTR",
+                    "type": "CTR",
+                    "class": "D",
+                    "geometry": [
+                        {"lower": "SFC", "upper": "FL65", "boundary": square("5120", "00240")}
+                    ]
+                },
+                {
+                    "name": "D123 IMBER",
+                    "type": "D",
+                    "geometry": [{
+                        "lower": "SFC",
+                        "upper": "FL100",
+                        "boundary": [{"circle": {"centre": "512000N 0010000W", "radius": "2 nm"}}]
+                    }]
+                },
+                {
+                    "name": "D125 LARKHILL",
+                    "type": "D",
+                    "hours": "0900-1700",
+                    "geometry": [
+                        {"lower": "SFC", "upper": "5000 ft", "boundary": square("5110", "00150")}
+                    ]
+                },
+                {
+                    "name": "ROYAL INTERNATIONAL AIR TATTOO",
+                    "type": "D_OTHER",
+                    "rules": ["NOTAM"],
+                    "geometry": [
+                        {"lower": "SFC", "upper": "FL75", "boundary": square("5140", "00145")}
+                    ]
+                },
+                {
+                    "name": "LASHAM",
+                    "type": "OTHER",
+                    "localtype": "GLIDER",
+                    "geometry": [{
+                        "lower": "SFC",
+                        "upper": "2000 ft",
+                        "boundary": [{"circle": {"centre": "511110N 0010200W", "radius": "1 nm"}}]
+                    }]
+                },
+                {
+                    "name": "NYMPSFIELD",
+                    "type": "OTHER",
+                    "localtype": "GLIDER",
+                    "geometry": [{
+                        "lower": "SFC",
+                        "upper": "2000 ft",
+                        "boundary": [{"circle": {"centre": "514250N 0021700W", "radius": "1 nm"}}]
+                    }]
+                },
+                {
+                    "name": "EDEN WAVE",
+                    "type": "D_OTHER",
+                    "localtype": "GLIDER",
+                    "geometry": [
+                        {"lower": "FL105", "upper": "FL245", "boundary": square("5430", "00230")}
+                    ]
+                },
+                {
+                    "name": "SOLENT CTA",
+                    "type": "CTA",
+                    "class": "D",
+                    "geometry": [
+                        {"lower": "2000 ft", "upper": "3500 ft", "boundary": square("5050", "00120")},
+                        {"lower": "3500 ft", "upper": "FL65", "boundary": square("5050", "00120")}
+                    ]
+                }
+            ],
+            "rat": [{
+                "name": "ROYAL WEDDING",
+                "type": "OTHER",
+                "localtype": "RAT",
+                "date": "2026-10-10",
+                "geometry": [
+                    {"lower": "SFC", "upper": "FL100", "boundary": square("5130", "00005")}
+                ]
+            }],
+            "loa": [{
+                "name": "CAMBRIDGE RAZ",
+                "areas": [{
+                    "name": "RAZ",
+                    "add": [{
+                        "name": "CAMBRIDGE RAZ",
+                        "type": "D_OTHER",
+                        "localtype": "GLIDER",
+                        "geometry": [
+                            {"lower": "SFC", "upper": "FL65", "boundary": square("5210", "00010")}
+                        ]
+                    }]
+                }]
+            }],
+            "obstacle": [{"elevation": "1083 ft", "name": "MENDIP MAST", "position": "511410N 0023700W"}],
+            "service": [{"callsign": "BRISTOL RADAR", "frequency": 125.65, "controls": ["bristol-ctr"]}],
+            "release": {
+                "airac_date": "2026-10-01T00:00:00Z",
+                "timestamp": "2026-09-20T10:00:00Z",
+                "schema_version": 1,
+                "note": "Test data",
+                "commit": "abc123"
+            }
+        }))
+        .unwrap()
+    }
+
+    // Fixed generation time, 12:00 UTC
+    pub(crate) fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 15, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn author_header() {
+        let yaixm = fixture();
+        let mut settings = Settings::default();
+        for format in [Format::OpenAir, Format::Tnp] {
+            settings.format = format;
+
+            settings.author = None;
+            let data = generate(&yaixm, &settings, "test", now()).unwrap().data;
+            assert!(!data.contains("Generated by asselect"));
+            assert!(data.contains("Produced: 2026-10-15T12:00:00+00:00"));
+
+            settings.author = Some("Fred".to_string());
+            let data = generate(&yaixm, &settings, "test", now()).unwrap().data;
+            assert!(data.contains("Generated by asselect for Fred\n"));
+        }
+    }
+
+    #[test]
+    fn active_now() {
+        let yaixm = fixture();
+        let settings = Settings {
+            active_now: true,
+            ..Settings::default()
+        };
+
+        // D125 LARKHILL is active 0900-1700
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN D125 LARKHILL\n"));
+
+        let evening = Utc.with_ymd_and_hms(2026, 10, 15, 20, 0, 0).unwrap();
+        let data = generate(&yaixm, &settings, "", evening).unwrap().data;
+        assert!(!data.contains("D125 LARKHILL"));
+        assert!(data.contains("AN D123 IMBER\n"));
+    }
+
+    #[test]
+    fn home_radius() {
+        let yaixm = fixture();
+        let mut settings = Settings {
+            home_radius_nm: Some(30),
+            ..Settings::default()
+        };
+
+        // Radius is ignored without a home airfield
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN BRISTOL CTR\n"));
+
+        settings.home = Some("LASHAM".to_string());
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN D123 IMBER\n"));
+        assert!(data.contains("AN SOLENT CTA\n"));
+        assert!(!data.contains("BRISTOL CTR"));
+        assert!(!data.contains("ROYAL INTERNATIONAL AIR TATTOO"));
+    }
+
+    #[test]
+    fn gliding_sites() {
+        let yaixm = fixture();
+        let mut settings = Settings {
+            gliding: Some(AirType::Gliding),
+            ..Settings::default()
+        };
+
+        // No sites selected includes them all
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN LASHAM"));
+        assert!(data.contains("AN NYMPSFIELD"));
+
+        settings.gliding_sites.insert("NYMPSFIELD".to_string());
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(!data.contains("AN LASHAM"));
+        assert!(data.contains("AN NYMPSFIELD"));
+    }
+
+    #[test]
+    fn type_cap() {
+        let yaixm = fixture();
+        let solent = |settings: &Settings| {
+            let data = generate(&yaixm, settings, "", now()).unwrap().data;
+            data.matches("AN SOLENT CTA\n").count()
+        };
+
+        // SOLENT CTA layers have bases at 2000 ft and 3500 ft
+        let mut settings = Settings::default();
+        assert_eq!(solent(&settings), 2);
+
+        settings.type_caps.insert(AirType::ClassD, 30);
+        assert_eq!(solent(&settings), 1);
+
+        // Cap above the maximum level doesn't raise it
+        settings.max_level = 30;
+        settings.type_caps.insert(AirType::ClassD, 100);
+        assert_eq!(solent(&settings), 1);
+    }
+
+    #[test]
+    fn rat_cut_off() {
+        let yaixm = fixture();
+        let mut settings = Settings {
+            rat: vec!["ROYAL WEDDING".to_string()],
+            ..Settings::default()
+        };
+
+        // ROYAL WEDDING is dated 2026-10-10
+        for (from, included) in [
+            ("2026-10-09", true),
+            ("2026-10-10", true),
+            ("2026-10-11", false),
+        ] {
+            settings.rat_from = Some(from.to_string());
+            let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+            assert_eq!(data.contains("AN ROYAL WEDDING\n"), included);
+        }
+    }
+
+    #[test]
+    fn openair_classes() {
+        for (air_type, class) in [
+            (AirType::Awy, "A"),
+            (AirType::ClassE, "E"),
+            (AirType::ClassF, "F"),
+            (AirType::Gliding, "W"),
+            (AirType::Rmz, "RMZ"),
+            (AirType::Tmz, "TMZ"),
+        ] {
+            assert_eq!(openair_class(air_type), class);
+        }
+    }
+
+    #[test]
+    fn validate_selected() {
+        let mut yaixm = fixture();
+        let imber = yaixm
+            .airspace
+            .iter()
+            .position(|feature| feature.name == "D123 IMBER")
+            .unwrap();
+        yaixm.airspace[imber].geometry[0].upper = "FL1OO".to_string();
+
+        assert_eq!(
+            generate(&yaixm, &Settings::default(), "", now()).err(),
+            Some(WriterError::BadAltitude {
+                name: "D123 IMBER".to_string(),
+                level: "FL1OO".to_string()
+            })
+        );
+
+        // Unselected airspace isn't checked
+        let mut settings = Settings::default();
+        settings.exclude.insert("D123 IMBER".to_string());
+        assert!(generate(&yaixm, &settings, "", now()).is_ok());
+
+        yaixm.airspace[imber].geometry[0].boundary.clear();
+        assert_eq!(
+            generate(&yaixm, &Settings::default(), "", now()).err(),
+            Some(WriterError::MissingGeometry("D123 IMBER".to_string()))
+        );
+    }
+
+    #[test]
+    fn level_formats() {
+        let mut yaixm = fixture();
+        let imber = yaixm
+            .airspace
+            .iter_mut()
+            .find(|feature| feature.name == "D123 IMBER")
+            .unwrap();
+        imber.geometry[0].lower = "GND".to_string();
+        imber.geometry[0].upper = "UNL".to_string();
+
+        let data = generate(&yaixm, &Settings::default(), "", now())
+            .unwrap()
+            .data;
+        assert!(data.contains("AN D123 IMBER\nAL SFC\nAH UNL\n"));
+
+        assert_eq!(format_level("2000 ft amsl"), "2000 ft");
+        assert_eq!(format_level("1500 ft agl"), "1500 ft AGL");
+        assert_eq!(format_level("FL195"), "FL195");
+    }
+
+    #[test]
+    fn altitude_ref() {
+        let yaixm = fixture();
+        for (altitude_ref, levels) in [
+            (AltitudeRef::AsIs, "AL SFC\nAH 5000 ft\n"),
+            (AltitudeRef::PreferAgl, "AL 0 ft AGL\nAH 5000 ft\n"),
+            (AltitudeRef::PreferAmsl, "AL SFC\nAH 5000 ft AMSL\n"),
+        ] {
+            let settings = Settings {
+                altitude_ref,
+                ..Settings::default()
+            };
+            let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+            assert!(data.contains(&format!("AN D125 LARKHILL\n{}", levels)));
+        }
+    }
+
+    #[test]
+    fn stacked_layers() {
+        let data = generate(&fixture(), &Settings::default(), "", now())
+            .unwrap()
+            .data;
+
+        // SOLENT CTA has two volumes with the same boundary
+        let blocks = data
+            .split("*\n")
+            .filter(|block| block.contains("AN SOLENT CTA\n"))
+            .collect::<Vec<&str>>();
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks[0].contains("AL 2000 ft\nAH 3500 ft\n"));
+        assert!(blocks[1].contains("AL 3500 ft\nAH FL65\n"));
+    }
+
+    #[test]
+    fn preview_limit() {
+        let data = generate_preview(&Settings::default(), &fixture(), 3, now());
+
+        assert_eq!(data.matches("AC ").count(), 3);
+        assert!(data.ends_with("* ... truncated\n"));
+        assert!(!data.contains("SOLENT CTA"));
+
+        let data = generate_preview(&Settings::default(), &fixture(), 20, now());
+        assert_eq!(data.matches("AC ").count(), 6);
+        assert!(!data.contains("truncated"));
+    }
+
+    #[test]
+    fn empty_rat_only() {
+        let settings = Settings {
+            format: Format::RatOnly,
+            ..Settings::default()
+        };
+        let generated = generate(&fixture(), &settings, "", now()).unwrap();
+
+        assert_eq!(generated.count, 0);
+        assert!(generated.data.starts_with("* UK Airspace\n"));
+        assert!(!generated.data.contains("AC "));
+    }
+
+    #[test]
+    fn deterministic_output() {
+        let yaixm = fixture();
+        let selection = |names: &[&str]| {
+            let mut settings = Settings::default();
+            for name in names {
+                settings.loa.insert(name.to_string());
+                settings.wave.insert(name.to_string());
+                settings.exclude.insert(name.to_string());
+            }
+            settings.loa.insert("CAMBRIDGE RAZ".to_string());
+            settings.wave.insert("EDEN WAVE".to_string());
+            settings.rat.push("ROYAL WEDDING".to_string());
+            settings
+        };
+        let first = selection(&["A", "B", "C", "D"]);
+        let second = selection(&["D", "C", "B", "A"]);
+
+        let data = generate(&yaixm, &first, "", now()).unwrap().data;
+        assert!(data.contains("AN CAMBRIDGE RAZ\n"));
+        assert!(data.contains("AN EDEN WAVE\n"));
+        assert!(data.contains("AN ROYAL WEDDING\n"));
+        assert_eq!(generate(&yaixm, &second, "", now()).unwrap().data, data);
+    }
+
+    #[test]
+    fn progress() {
+        let yaixm = fixture();
+        for format in [Format::OpenAir, Format::Kml, Format::Csv] {
+            let settings = Settings {
+                format,
+                ..Settings::default()
+            };
+            let mut values = Vec::new();
+            generate_with_progress(&yaixm, &settings, "", now(), |value| values.push(value))
+                .unwrap();
+
+            let (airspace, _) = select_airspace(&yaixm, &settings, now());
+            assert_eq!(values.len(), airspace.len() + 1);
+            assert_eq!(values.first(), Some(&0.0));
+            assert_eq!(values.last(), Some(&1.0));
+            assert!(values.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+    }
+
+    #[test]
+    fn wave_clip() {
+        let settings = |wave_clip| {
+            let mut settings = Settings {
+                format: Format::Csv,
+                max_level: 195,
+                wave_clip,
+                ..Settings::default()
+            };
+            settings.wave.insert("EDEN WAVE".to_string());
+            settings
+        };
+        let row = |wave_clip| {
+            let data = generate(&fixture(), &settings(wave_clip), "", now())
+                .unwrap()
+                .data;
+            data.lines()
+                .find(|line| line.starts_with("EDEN WAVE,"))
+                .map(|line| line.to_string())
+        };
+
+        assert_eq!(
+            row(WaveClip::Clip).as_deref(),
+            Some("EDEN WAVE,W,10500,FL,19500,FL,Wave")
+        );
+        assert_eq!(
+            row(WaveClip::Keep).as_deref(),
+            Some("EDEN WAVE,W,10500,FL,24500,FL,Wave")
+        );
+        assert_eq!(row(WaveClip::Drop), None);
+    }
+
+    #[test]
+    fn wave_min_level() {
+        let settings = |format, wave_min_fl| {
+            let mut settings = Settings {
+                format,
+                wave_min_fl,
+                ..Settings::default()
+            };
+            settings.wave.insert("EDEN WAVE".to_string());
+            settings
+        };
+        let wave = |wave_min_fl| {
+            let settings = settings(Format::GeoJson, wave_min_fl);
+            let data = generate(&fixture(), &settings, "", now()).unwrap().data;
+            let collection = serde_json::from_str::<serde_json::Value>(&data).unwrap();
+            collection["features"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|feature| feature["properties"]["name"] == "EDEN WAVE")
+                .map(|feature| feature["properties"].clone())
+        };
+
+        // Base raised to the minimum wave level, on all writers
+        let properties = wave(Some(120)).unwrap();
+        assert_eq!(properties["lower"], "FL120");
+        assert_eq!(properties["upper"], "FL245");
+        let data = generate(&fixture(), &settings(Format::OpenAir, Some(120)), "", now())
+            .unwrap()
+            .data;
+        assert!(data.contains("AN EDEN WAVE\nAL FL120\n"));
+
+        // Base already above the minimum level
+        assert_eq!(wave(Some(100)).unwrap()["lower"], "FL105");
+
+        // Box entirely below the minimum level
+        assert_eq!(wave(Some(245)), None);
+    }
+
+    #[test]
+    fn split_by_type() {
+        let yaixm = fixture();
+        let settings = Settings::default();
+        let (entries, truncated) = generate_split(&yaixm, &settings, "uk", "", now()).unwrap();
+
+        let names = entries
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, ["uk_classd.txt", "uk_danger.txt", "uk_classg.txt"]);
+        assert!(!truncated);
+
+        // Each file has only its own type, with the unsplit header
+        let combined = generate(&yaixm, &settings, "", now()).unwrap().data;
+        let header = &combined[..combined.find("*\nAC ").unwrap()];
+        for ((_, data), class) in entries.iter().zip(["D", "Q", "G"]) {
+            assert!(data.starts_with(header));
+            assert!(data
+                .lines()
+                .filter(|line| line.starts_with("AC "))
+                .all(|line| line == format!("AC {}", class)));
+        }
+        assert_eq!(entries[0].1.matches("AC D\n").count(), 3);
+        assert!(entries[1].1.contains("AN D123 IMBER\n"));
+        assert!(entries[1].1.contains("AN D125 LARKHILL\n"));
+
+        let settings = Settings {
+            max_features: Some(2),
+            ..Settings::default()
+        };
+        let (_, truncated) = generate_split(&yaixm, &settings, "uk", "", now()).unwrap();
+        assert!(truncated);
+    }
+
+    #[test]
+    fn bundle() {
+        let settings = Settings {
+            crlf: true,
+            ..Settings::default()
+        };
+        let entries = generate_bundle(
+            &fixture(),
+            &settings,
+            &[Format::OpenAir, Format::Csv],
+            "uk2026",
+            "",
+            now(),
+        )
+        .unwrap();
+
+        let names = entries
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, ["uk2026_openair.txt", "uk2026_csv.csv"]);
+        assert!(entries[0].1.contains("AC D\r\nAN BRISTOL CTR\r\n"));
+        assert!(entries[1]
+            .1
+            .starts_with("name,type,lower,lower_ref,upper,upper_ref,category\r\n"));
+    }
+
+    #[test]
+    fn home_match() {
+        let yaixm = fixture();
+        let sites = yaixm
+            .airspace
+            .iter()
+            .filter(|x| x.local_type == Some(LocalType::Glider) && x.icao_type == IcaoType::Other)
+            .collect::<Vec<&Feature>>();
+        let home = |name| resolve_home(name, &sites).map(|site| site.name.as_str());
+
+        assert_eq!(edit_distance("lasham", "lashem"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+
+        // Exact, ignoring case and space
+        assert_eq!(home(" lasham "), Some("LASHAM"));
+
+        // Near misses
+        assert_eq!(home("LASHEM"), Some("LASHAM"));
+        assert_eq!(home("Nympsfeild"), Some("NYMPSFIELD"));
+
+        // No match
+        assert_eq!(home("DUNSTABLE"), None);
+        assert_eq!(home("LAS"), None);
+        assert_eq!(site_centre(&yaixm, "DUNSTABLE"), None);
+        assert!(site_centre(&yaixm, "lashem").is_some());
+    }
+
+    #[test]
+    fn exclude_danger() {
+        let yaixm = fixture();
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN D123 IMBER\n"));
+
+        settings.exclude.insert("D123 IMBER".to_string());
+        let generated = generate(&yaixm, &settings, "", now()).unwrap();
+        assert!(!generated.data.contains("D123 IMBER"));
+        assert!(generated.data.contains("AN D125 LARKHILL\n"));
+
+        // Other writers too
+        settings.format = Format::GeoJson;
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(!data.contains("D123 IMBER"));
+        assert!(data.contains("D125 LARKHILL"));
+    }
+
+    #[test]
+    fn radio() {
+        let yaixm = fixture();
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(!data.contains("\nAF "));
+        assert!(!data.contains("\nAG "));
+
+        settings.radio = true;
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN BRISTOL CTR 125.650\nAF 125.650\nAG BRISTOL RADAR\nAL SFC\n"));
+    }
+
+    #[test]
+    fn radio_comment() {
+        let yaixm = fixture();
+        let radio = "AN BRISTOL CTR 125.650\nAF 125.650\nAG BRISTOL RADAR\nAL SFC\n";
+
+        // Comment only
+        let settings = Settings {
+            radio_as_comment: true,
+            ..Default::default()
+        };
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN BRISTOL CTR\n* 125.650 BRISTOL RADAR\nAL SFC\n"));
+        assert!(!data.contains("\nAF "));
+
+        // Radio takes precedence over the comment
+        let settings = Settings {
+            radio: true,
+            ..settings
+        };
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains(radio));
+        assert!(!data.contains("* 125.650"));
+
+        // Radio only
+        let settings = Settings {
+            radio_as_comment: false,
+            ..settings
+        };
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains(radio));
+        assert!(!data.contains("* 125.650"));
+    }
+
+    #[test]
+    fn min_level() {
+        let yaixm = fixture();
+        let settings = Settings {
+            min_level: 40,
+            ..Default::default()
+        };
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+
+        // Lower SOLENT CTA layer tops out at 3500 ft
+        assert!(!data.contains("AL 2000 ft\nAH 3500 ft\n"));
+        assert!(data.contains("AN SOLENT CTA\nAL 3500 ft\nAH FL65\n"));
+        assert!(data.contains("AN D125 LARKHILL\n"));
+
+        // Upper limit at the minimum level is still in band
+        let settings = Settings {
+            min_level: 50,
+            ..Default::default()
+        };
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN D125 LARKHILL\n"));
+    }
+
+    #[test]
+    fn low_only() {
+        let yaixm = fixture();
+        let settings = Settings {
+            low_only: true,
+            ..Default::default()
+        };
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+
+        // Upper SOLENT CTA layer starts above LOW_ONLY_LEVEL
+        assert!(data.contains("AN SOLENT CTA\nAL 2000 ft\nAH 3500 ft\n"));
+        assert!(!data.contains("AL 3500 ft"));
+        assert!(data.contains("AN BRISTOL CTR\n"));
+    }
+
+    #[test]
+    fn max_features() {
+        let yaixm = fixture();
+        let count = |data: &str| data.matches("\nAN ").count();
+
+        // Five features, SOLENT CTA has two layers
+        let settings = Settings {
+            max_features: Some(5),
+            ..Default::default()
+        };
+        let generated = generate(&yaixm, &settings, "", now()).unwrap();
+        assert!(!generated.truncated);
+        assert_eq!(count(&generated.data), 6);
+
+        // Highest based feature is dropped first
+        let settings = Settings {
+            max_features: Some(4),
+            ..Default::default()
+        };
+        let generated = generate(&yaixm, &settings, "", now()).unwrap();
+        assert!(generated.truncated);
+        assert_eq!(count(&generated.data), 4);
+        assert!(!generated.data.contains("SOLENT CTA"));
+    }
+
+    #[test]
+    fn inactive_danger() {
+        let mut yaixm = fixture();
+        yaixm.airspace.push(
+            serde_json::from_value(json!({
+                "name": "D127 DISUSED",
+                "type": "D",
+                "rules": ["INACTIVE"],
+                "geometry": [{
+                    "lower": "SFC",
+                    "upper": "FL50",
+                    "boundary": [{"circle": {"centre": "512000N 0020000W", "radius": "1 nm"}}]
+                }]
+            }))
+            .unwrap(),
+        );
+
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN D127 DISUSED\n"));
+
+        settings.exclude_inactive_danger = true;
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(!data.contains("D127 DISUSED"));
+        assert!(data.contains("AN D123 IMBER\n"));
+    }
+
+    #[test]
+    fn require_frequency() {
+        let yaixm = fixture();
+        let settings = Settings {
+            require_frequency: true,
+            ..Default::default()
+        };
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+
+        // Only BRISTOL CTR has a service frequency
+        assert!(data.contains("AN BRISTOL CTR\n"));
+        assert_eq!(data.matches("\nAN ").count(), 1);
+    }
+
+    #[test]
+    fn obstacle_min_height() {
+        let yaixm = fixture();
+        let mut settings = Settings {
+            obstacle: Some(AirType::Other),
+            obstacle_min_ft: Some(1000),
+            ..Default::default()
+        };
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN MENDIP MAST\nAL SFC\nAH 1083 ft\n"));
+
+        // Obstacle is 1083 ft
+        settings.obstacle_min_ft = Some(1100);
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(!data.contains("MENDIP MAST"));
+    }
+
+    #[test]
+    fn matz() {
+        let mut yaixm = fixture();
+        yaixm.airspace.push(
+            serde_json::from_value(json!({
+                "name": "BOSCOMBE DOWN",
+                "type": "OTHER",
+                "localtype": "MATZ",
+                "geometry": [{
+                    "lower": "SFC",
+                    "upper": "3000 ft",
+                    "boundary": [{"circle": {"centre": "510900N 0014500W", "radius": "5 nm"}}]
+                }]
+            }))
+            .unwrap(),
+        );
+
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AC MATZ\nAN BOSCOMBE DOWN MATZ\n"));
+
+        settings.matz = Some(AirType::ClassD);
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AC D\nAN BOSCOMBE DOWN MATZ\n"));
+
+        settings.matz = None;
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(!data.contains("BOSCOMBE DOWN MATZ"));
+    }
+
+    #[test]
+    fn atz_buffer() {
+        let mut yaixm = fixture();
+        yaixm.airspace.push(
+            serde_json::from_value(json!({
+                "name": "OLD SARUM ATZ",
+                "type": "ATZ",
+                "geometry": [{
+                    "lower": "SFC",
+                    "upper": "2000 ft",
+                    "boundary": [{"circle": {"centre": "510600N 0014700W", "radius": "2 nm"}}]
+                }]
+            }))
+            .unwrap(),
+        );
+
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("V X=51:06:00 N 001:47:00 W\nDC 2\n"));
+
+        // Only the ATZ is widened
+        settings.atz_buffer_nm = Some(0.5);
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("V X=51:06:00 N 001:47:00 W\nDC 2.5\n"));
+        assert!(data.contains("V X=51:20:00 N 001:00:00 W\nDC 2\n"));
+    }
+
+    #[test]
+    fn dedupe_names() {
+        let yaixm = fixture();
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert_eq!(data.matches("AN SOLENT CTA\n").count(), 2);
+
+        // Second SOLENT CTA layer is numbered
+        settings.dedupe_names = true;
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN SOLENT CTA\nAL 2000 ft\n"));
+        assert!(data.contains("AN SOLENT CTA (2)\nAL 3500 ft\n"));
+        assert!(data.contains("AN BRISTOL CTR\n"));
+    }
+
+    #[test]
+    fn colorize() {
+        let yaixm = fixture();
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(!data.contains("\nSP "));
+
+        // Pen colour follows the name
+        settings.colorize = true;
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN BRISTOL CTR\nSP 0,2,0,0,255\nSB -1,-1,-1\nAL SFC\n"));
+        assert!(data.contains("AN D123 IMBER\nSP 0,2,255,128,0\nSB -1,-1,-1\n"));
+    }
+
+    #[test]
+    fn name_prefix() {
+        let yaixm = fixture();
+        let settings = Settings {
+            name_prefix: Some("UK".to_string()),
+            ..Default::default()
+        };
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN UK BRISTOL CTR\n"));
+        assert!(data.contains("AN UK D123 IMBER\n"));
+        assert!(!data.contains("AN BRISTOL CTR\n"));
+    }
+
+    #[test]
+    fn crlf() {
+        let yaixm = fixture();
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert_eq!(line_endings(&data, &settings), data);
+
+        settings.crlf = true;
+        let crlf = line_endings(&data, &settings);
+        assert!(crlf.contains("AN BRISTOL CTR\r\nAL SFC\r\n"));
+        assert_eq!(crlf.matches('\n').count(), crlf.matches("\r\n").count());
+
+        // Already converted text is unchanged
+        assert_eq!(line_endings(&crlf, &settings), crlf);
+    }
+
+    #[test]
+    fn lk8000_activity() {
+        let yaixm = fixture();
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(!data.contains("\nAY "));
+
+        settings.format = Format::OpenAirLk;
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AY 0900-1700\nAN D125 LARKHILL\n"));
+        assert!(data.contains("AY NOTAM\nAN ROYAL INTERNATIONAL AIR TATTOO (NOTAM)\n"));
+        assert_eq!(data.matches("\nAY ").count(), 2);
+    }
+
+    #[test]
+    fn freq_decimals() {
+        assert_eq!(format_freq(118.105, 3), "118.105");
+        assert_eq!(format_freq(118.105, 2), "118.10");
+
+        let yaixm = fixture();
+        let settings = Settings {
+            radio: true,
+            freq_decimals: 2,
+            ..Default::default()
+        };
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AF 125.65\nAG BRISTOL RADAR\n"));
+    }
+
+    #[test]
+    fn include_notes() {
+        let mut yaixm = fixture();
+        let imber = yaixm
+            .airspace
+            .iter_mut()
+            .find(|feature| feature.name == "D123 IMBER")
+            .unwrap();
+        imber.notes = Some("Range active during published hours".to_string());
+
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(!data.contains("Range active"));
+        assert!(!data.contains("* Rules:"));
+
+        settings.include_notes = true;
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("*\n* Range active during published hours\nAC Q\nAN D123 IMBER\n"));
+        assert!(data.contains("*\n* Rules: NOTAM\nAC G\n"));
+    }
+
+    #[test]
+    fn competition_area() {
+        let yaixm = fixture();
+        let mut settings = Settings {
+            competition_center: Some("LASHAM".to_string()),
+            competition_radius_nm: Some(15),
+            ..Default::default()
+        };
+
+        // Ignored outside competition format
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN BRISTOL CTR\n"));
+        assert!(data.contains("AN D123 IMBER\n"));
+
+        settings.format = Format::Competition;
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AN D123 IMBER\n"));
+        assert!(!data.contains("BRISTOL CTR"));
+        assert!(!data.contains("D125 LARKHILL"));
+        assert!(!data.contains("SOLENT CTA"));
+    }
+
+    #[test]
+    fn label_points() {
+        let yaixm = fixture();
+        let mut settings = Settings::default();
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(!data.contains("\nAT "));
+
+        // Centre of the BRISTOL CTR square
+        settings.label_points = true;
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        assert!(data.contains("AH FL65\nAT 51:20:15 N 002:40:15 W\nDP "));
+        assert_eq!(data.matches("\nAT ").count(), data.matches("\nAN ").count());
+    }
+}
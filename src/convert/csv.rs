@@ -0,0 +1,121 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use super::{volume_name, volume_type};
+use crate::altitude::{parse_altitude, Reference};
+use crate::state::Settings;
+use crate::yaixm::{Feature, LocalType, Rule, Volume};
+use std::borrow::Cow;
+
+// Quote field if it contains a separator or quote
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Level in feet and its reference (AGL, AMSL or FL), empty if the level
+// can't be parsed
+fn level(value: &str) -> (String, &'static str) {
+    match parse_altitude(value) {
+        Ok(alt) => (
+            alt.feet().to_string(),
+            match alt.reference {
+                Reference::Standard => "FL",
+                Reference::Amsl => "AMSL",
+                Reference::Agl => "AGL",
+            },
+        ),
+        Err(_) => (String::new(), ""),
+    }
+}
+
+// Optional airspace category
+fn category(feature: &Feature, settings: &Settings) -> &'static str {
+    let loa = feature
+        .rules
+        .iter()
+        .flatten()
+        .any(|rule| *rule == Rule::Loa);
+
+    if loa {
+        "LOA"
+    } else if feature.local_type == Some(LocalType::Rat) {
+        "RAT"
+    } else if settings.wave.contains(&feature.name) {
+        "Wave"
+    } else {
+        ""
+    }
+}
+
+// Generate CSV index of airspace volumes
+pub fn csv<'a>(
+    volumes: impl IntoIterator<Item = (&'a Feature, usize, Cow<'a, Volume>)>,
+    settings: &Settings,
+) -> String {
+    let mut output = "name,type,lower,lower_ref,upper,upper_ref,category\n".to_string();
+    for (feature, n, volume) in volumes {
+        let (lower, lower_ref) = level(&volume.lower);
+        let (upper, upper_ref) = level(&volume.upper);
+
+        output.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            field(&volume_name(feature, &volume, n, settings)),
+            volume_type(feature, &volume, settings),
+            lower,
+            lower_ref,
+            upper,
+            upper_ref,
+            category(feature, settings)
+        ));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::generate;
+    use crate::convert::tests::{fixture, now};
+    use crate::state::Format;
+
+    #[test]
+    fn csv_rows() {
+        let settings = Settings {
+            format: Format::Csv,
+            ..Settings::default()
+        };
+        let output = generate(&fixture(), &settings, "", now()).unwrap().data;
+
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next(),
+            Some("name,type,lower,lower_ref,upper,upper_ref,category")
+        );
+        assert!(output
+            .lines()
+            .any(|line| line == "D123 IMBER,Q,0,AGL,10000,FL,"));
+    }
+
+    #[test]
+    fn bad_level() {
+        assert_eq!(level("FL65"), ("6500".to_string(), "FL"));
+        assert_eq!(level("2000 ft"), ("2000".to_string(), "AMSL"));
+        assert_eq!(level("garbage"), (String::new(), ""));
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::geometry::parse_latlon;
+use crate::yaixm::{Boundary, Feature, IcaoType, LocalType, Volume};
+use std::borrow::Cow;
+
+// CUP style for gliding airfield
+const GLIDING_STYLE: u8 = 4;
+
+// CUP degrees/minutes, rounded to thousandths of a minute before
+// splitting so minutes never round up to 60
+fn format_angle(value: f64, deg_width: usize, pos: char, neg: char) -> String {
+    let hemi = if value < 0.0 { neg } else { pos };
+    let thousandths = (value.abs() * 60_000.0).round() as u32;
+    let deg = thousandths / 60_000;
+    let min = (thousandths % 60_000) as f64 / 1000.0;
+
+    format!("{:0w$}{:06.3}{}", deg, min, hemi, w = deg_width)
+}
+
+// Short waypoint code from name
+fn code(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(6)
+        .collect::<String>()
+        .to_uppercase()
+}
+
+// Generate SeeYou CUP waypoints for gliding sites
+pub fn cup<'a>(volumes: impl IntoIterator<Item = (&'a Feature, usize, Cow<'a, Volume>)>) -> String {
+    let mut output = "name,code,country,lat,lon,elev,style,rwdir,rwlen,freq,desc\n".to_string();
+    for (feature, n, volume) in volumes {
+        if feature.icao_type != IcaoType::Other
+            || feature.local_type != Some(LocalType::Glider)
+            || n > 0
+        {
+            continue;
+        }
+
+        if let Some(Boundary::Circle(circle)) = volume.boundary.first() {
+            let (lat, lon) = parse_latlon(&circle.centre);
+            let freq = volume
+                .frequency
+                .map(|f| format!("{:.3}", f))
+                .unwrap_or_default();
+
+            // The source has no site elevations, so elev is left empty
+            output.push_str(&format!(
+                "\"{}\",{},GB,{},{},,{},,,{},\n",
+                feature.name.replace('"', "'"),
+                code(&feature.name),
+                format_angle(lat, 2, 'N', 'S'),
+                format_angle(lon, 3, 'E', 'W'),
+                GLIDING_STYLE,
+                freq
+            ));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::generate;
+    use crate::convert::tests::{fixture, now};
+    use crate::state::{AirType, Format, Settings};
+
+    #[test]
+    fn cup_rows() {
+        let settings = Settings {
+            gliding: Some(AirType::Gliding),
+            format: Format::Cup,
+            ..Settings::default()
+        };
+        let output = generate(&fixture(), &settings, "", now()).unwrap().data;
+
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next(),
+            Some("name,code,country,lat,lon,elev,style,rwdir,rwlen,freq,desc")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("\"LASHAM\",LASHAM,GB,5111.167N,00102.000W,,4,,,,")
+        );
+        assert_eq!(lines.count(), 1);
+    }
+
+    #[test]
+    fn angle_carry() {
+        assert_eq!(
+            format_angle(51.0 + 59.9996 / 60.0, 2, 'N', 'S'),
+            "5200.000N"
+        );
+        assert_eq!(format_angle(-(1.0 + 0.5 / 60.0), 3, 'E', 'W'), "00100.500W");
+    }
+}
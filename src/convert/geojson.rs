@@ -0,0 +1,88 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use super::{class_airtype, volume_name, volume_type};
+use crate::geometry::boundary_ring;
+use crate::state::Settings;
+use crate::yaixm::{Feature, Volume};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+
+// Generate GeoJSON feature collection
+pub fn geojson<'a>(
+    volumes: impl IntoIterator<Item = (&'a Feature, usize, Cow<'a, Volume>)>,
+    settings: &Settings,
+) -> String {
+    let mut features: Vec<Value> = Vec::new();
+    for (feature, n, volume) in volumes {
+        let ring = boundary_ring(&volume.boundary, settings.arc_resolution)
+            .into_iter()
+            .map(|(lat, lon)| vec![lon, lat])
+            .collect::<Vec<Vec<f64>>>();
+
+        features.push(json!({
+            "type": "Feature",
+            "properties": {
+                "name": volume_name(feature, &volume, n, settings),
+                "type": class_airtype(volume_type(feature, &volume, settings)),
+                "lower": volume.lower,
+                "upper": volume.upper,
+            },
+            "geometry": {
+                "type": "Polygon",
+                "coordinates": [ring],
+            },
+        }));
+    }
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::generate;
+    use crate::convert::tests::{fixture, now};
+    use crate::state::Format;
+
+    #[test]
+    fn danger_circle() {
+        let settings = Settings {
+            format: Format::GeoJson,
+            ..Settings::default()
+        };
+        let output = generate(&fixture(), &settings, "", now()).unwrap().data;
+        let collection = serde_json::from_str::<Value>(&output).unwrap();
+
+        let danger = collection["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|feature| feature["properties"]["name"] == "D123 IMBER")
+            .unwrap();
+        assert_eq!(
+            danger["properties"],
+            json!({"name": "D123 IMBER", "type": "Danger", "lower": "SFC", "upper": "FL100"})
+        );
+
+        let ring = danger["geometry"]["coordinates"][0].as_array().unwrap();
+        assert!(ring.len() > 4);
+        assert_eq!(ring.first(), ring.last());
+    }
+}
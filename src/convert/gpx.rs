@@ -0,0 +1,120 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use super::{volume_name, volume_type, xml_escape};
+use crate::altitude::{parse_altitude, Unit};
+use crate::geometry::parse_latlon;
+use crate::state::Settings;
+use crate::yaixm::{Boundary, Feature, LocalType, Volume};
+use std::borrow::Cow;
+
+// Elevation in metres from altitude, e.g. "1234 ft"
+fn elevation(level: &str) -> Option<f64> {
+    parse_altitude(level)
+        .ok()
+        .filter(|alt| alt.unit == Unit::Feet)
+        .map(|alt| alt.metres())
+}
+
+// Generate GPX waypoints for obstacles and circular airspace
+pub fn gpx<'a>(
+    volumes: impl IntoIterator<Item = (&'a Feature, usize, Cow<'a, Volume>)>,
+    settings: &Settings,
+) -> String {
+    let mut output = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <gpx version=\"1.1\" creator=\"ASSelect\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n"
+        .to_string();
+
+    for (feature, n, volume) in volumes {
+        // Only point-like (circular) airspace has a meaningful waypoint
+        let centre = match volume.boundary.as_slice() {
+            [Boundary::Circle(circle)] => &circle.centre,
+            _ => continue,
+        };
+        let (lat, lon) = parse_latlon(centre);
+
+        output.push_str(&format!("  <wpt lat=\"{:.6}\" lon=\"{:.6}\">\n", lat, lon));
+        if feature.local_type == Some(LocalType::Obstacle) {
+            if let Some(ele) = elevation(&volume.upper) {
+                output.push_str(&format!("    <ele>{:.0}</ele>\n", ele));
+            }
+        }
+        output.push_str(&format!(
+            "    <name>{}</name>\n",
+            xml_escape(&volume_name(feature, &volume, n, settings))
+        ));
+        output.push_str(&format!(
+            "    <type>{}</type>\n",
+            volume_type(feature, &volume, settings)
+        ));
+        output.push_str("  </wpt>\n");
+    }
+
+    output.push_str("</gpx>\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::generate;
+    use crate::convert::tests::{fixture, now};
+    use crate::state::{AirType, Format};
+
+    // Waypoints as (name, type, elevation) from GPX output
+    fn waypoints(output: &str) -> Vec<(String, String, Option<String>)> {
+        let element = |wpt: &str, tag: &str| {
+            let start = wpt.find(&format!("<{}>", tag))? + tag.len() + 2;
+            let end = wpt.find(&format!("</{}>", tag))?;
+            Some(wpt[start..end].to_string())
+        };
+
+        output
+            .split("<wpt ")
+            .skip(1)
+            .map(|wpt| {
+                let wpt = &wpt[..wpt.find("</wpt>").unwrap()];
+                (
+                    element(wpt, "name").unwrap(),
+                    element(wpt, "type").unwrap(),
+                    element(wpt, "ele"),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gpx_waypoints() {
+        let settings = Settings {
+            format: Format::Gpx,
+            gliding: Some(AirType::Gliding),
+            obstacle: Some(AirType::Other),
+            ..Settings::default()
+        };
+        let output = generate(&fixture(), &settings, "", now()).unwrap().data;
+        assert!(output.ends_with("</gpx>\n"));
+
+        // Circular volumes only, obstacles with elevation in metres
+        let waypoints = waypoints(&output);
+        assert_eq!(waypoints.len(), 4);
+        assert!(waypoints.contains(&("D123 IMBER".to_string(), "Q".to_string(), None)));
+        assert!(waypoints.contains(&(
+            "MENDIP MAST".to_string(),
+            "OTHER".to_string(),
+            Some("330".to_string())
+        )));
+        assert!(!waypoints.iter().any(|(name, _, _)| name == "BRISTOL CTR"));
+    }
+}
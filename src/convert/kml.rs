@@ -0,0 +1,145 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use super::{volume_name, volume_type, xml_escape};
+use crate::altitude::parse_altitude;
+use crate::geometry::boundary_ring;
+use crate::state::Settings;
+use crate::yaixm::{Feature, Volume};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+// Style name and colour (KML aabbggrr) from OpenAir type
+fn style(openair_type: &str) -> (&'static str, &'static str) {
+    match openair_type {
+        "A" => ("ClassA", "ff0000ff"),
+        "B" => ("ClassB", "ff0040ff"),
+        "C" => ("ClassC", "ff0080ff"),
+        "D" => ("ClassD", "ffff0000"),
+        "E" => ("ClassE", "ffff8000"),
+        "F" => ("ClassF", "ff00c0c0"),
+        "G" => ("ClassG", "ff808080"),
+        "CTA" => ("Cta", "ffff0080"),
+        "CTR" => ("Ctr", "ffff00ff"),
+        "P" => ("Prohibited", "ff0000c0"),
+        "Q" => ("Danger", "ff0000a0"),
+        "R" => ("Restricted", "ff0060c0"),
+        "W" => ("Gliding", "ff00c000"),
+        "MATZ" => ("Matz", "ffc0c000"),
+        "RMZ" => ("Rmz", "ff00a0a0"),
+        "TMZ" => ("Tmz", "ff404040"),
+        _ => ("Other", "ff000000"),
+    }
+}
+
+// Altitude in metres for extruded polygons, if known
+fn altitude(level: &str) -> Option<f64> {
+    parse_altitude(level).ok().map(|alt| alt.metres())
+}
+
+// Generate KML document
+pub fn kml<'a>(
+    volumes: impl IntoIterator<Item = (&'a Feature, usize, Cow<'a, Volume>)>,
+    settings: &Settings,
+) -> String {
+    // Placemarks grouped by style
+    let mut folders: BTreeMap<&str, String> = BTreeMap::new();
+    let mut colours: BTreeMap<&str, &str> = BTreeMap::new();
+
+    for (feature, n, volume) in volumes {
+        let (style_id, colour) = style(volume_type(feature, &volume, settings));
+        colours.insert(style_id, colour);
+
+        let upper = altitude(&volume.upper);
+        let coords = boundary_ring(&volume.boundary, settings.arc_resolution)
+            .into_iter()
+            .map(|(lat, lon)| format!("{:.6},{:.6},{:.0}", lon, lat, upper.unwrap_or(0.0)))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        let mut placemark = String::new();
+        placemark.push_str("      <Placemark>\n");
+        placemark.push_str(&format!(
+            "        <name>{}</name>\n",
+            xml_escape(&volume_name(feature, &volume, n, settings))
+        ));
+        placemark.push_str(&format!(
+            "        <description>{} to {}</description>\n",
+            xml_escape(&volume.lower),
+            xml_escape(&volume.upper)
+        ));
+        placemark.push_str(&format!("        <styleUrl>#{}</styleUrl>\n", style_id));
+        placemark.push_str("        <Polygon>\n");
+        if upper.is_some() {
+            placemark.push_str("          <extrude>1</extrude>\n");
+            placemark.push_str("          <altitudeMode>absolute</altitudeMode>\n");
+        }
+        placemark.push_str(&format!(
+            "          <outerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></outerBoundaryIs>\n",
+            coords
+        ));
+        placemark.push_str("        </Polygon>\n");
+        placemark.push_str("      </Placemark>\n");
+
+        folders.entry(style_id).or_default().push_str(&placemark);
+    }
+
+    let mut output = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n\
+        <Document>\n  <name>ASSelect - UK Airspace</name>\n"
+        .to_string();
+
+    for (style_id, colour) in &colours {
+        output.push_str(&format!(
+            "  <Style id=\"{}\">\n    <LineStyle><color>{}</color><width>2</width></LineStyle>\n    <PolyStyle><color>40{}</color></PolyStyle>\n  </Style>\n",
+            style_id,
+            colour,
+            &colour[2..]
+        ));
+    }
+
+    for (style_id, placemarks) in &folders {
+        output.push_str(&format!("  <Folder>\n    <name>{}</name>\n", style_id));
+        output.push_str(placemarks);
+        output.push_str("  </Folder>\n");
+    }
+
+    output.push_str("</Document>\n</kml>\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::generate;
+    use crate::convert::tests::{fixture, now};
+    use crate::state::Format;
+
+    #[test]
+    fn class_style() {
+        let settings = Settings {
+            format: Format::Kml,
+            ..Settings::default()
+        };
+        let output = generate(&fixture(), &settings, "", now()).unwrap().data;
+
+        assert!(output.contains("  <Style id=\"ClassD\">\n    <LineStyle><color>ffff0000</color>"));
+        let placemark = output
+            .split("<Placemark>")
+            .find(|placemark| placemark.contains("<name>BRISTOL CTR</name>"))
+            .unwrap();
+        assert!(placemark.contains("<styleUrl>#ClassD</styleUrl>"));
+    }
+}
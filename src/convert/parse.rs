@@ -0,0 +1,184 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use std::fmt;
+
+// Airspace parsed from OpenAir data
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Airspace {
+    pub class: String,
+    pub name: String,
+    pub lower: String,
+    pub upper: String,
+    pub segments: usize,
+}
+
+// Errors parsing OpenAir data, with line number
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseError {
+    UnknownDirective(usize),
+    MissingClass(usize),
+    MissingValue(usize),
+    Incomplete(usize),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnknownDirective(n) => write!(f, "unknown directive at line {}", n),
+            ParseError::MissingClass(n) => write!(f, "missing AC before line {}", n),
+            ParseError::MissingValue(n) => write!(f, "missing value at line {}", n),
+            ParseError::Incomplete(n) => write!(f, "incomplete airspace before line {}", n),
+        }
+    }
+}
+
+// Check airspace has name, levels and boundary
+fn complete(airspace: &Airspace, line: usize) -> Result<(), ParseError> {
+    if airspace.name.is_empty()
+        || airspace.lower.is_empty()
+        || airspace.upper.is_empty()
+        || airspace.segments == 0
+    {
+        Err(ParseError::Incomplete(line))
+    } else {
+        Ok(())
+    }
+}
+
+// Parse the subset of OpenAir written by this program
+pub fn parse_openair(data: &str) -> Result<Vec<Airspace>, ParseError> {
+    let mut airspace = Vec::new();
+    let mut current: Option<Airspace> = None;
+
+    for (n, line) in data.lines().enumerate() {
+        let n = n + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('*') {
+            continue;
+        }
+
+        let (directive, value) = line.split_once(' ').unwrap_or((line, ""));
+        let value = value.trim();
+
+        if directive == "AC" {
+            if let Some(prev) = current.take() {
+                complete(&prev, n)?;
+                airspace.push(prev);
+            }
+            if value.is_empty() {
+                return Err(ParseError::MissingValue(n));
+            }
+            current = Some(Airspace {
+                class: value.to_string(),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let cur = current.as_mut().ok_or(ParseError::MissingClass(n))?;
+        match directive {
+            "AN" | "AL" | "AH" | "AT" | "DP" | "DC" | "DB" | "V" | "AF" | "AG" | "AY" | "SP"
+            | "SB"
+                if value.is_empty() =>
+            {
+                return Err(ParseError::MissingValue(n));
+            }
+            "AN" => cur.name = value.to_string(),
+            "AL" => cur.lower = value.to_string(),
+            "AH" => cur.upper = value.to_string(),
+            "DP" | "DC" | "DB" => cur.segments += 1,
+            "AT" | "V" | "AF" | "AG" | "AY" | "SP" | "SB" => (),
+            _ => return Err(ParseError::UnknownDirective(n)),
+        }
+    }
+
+    if let Some(last) = current {
+        complete(&last, data.lines().count())?;
+        airspace.push(last);
+    }
+    Ok(airspace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::tests::{fixture, now};
+    use crate::convert::{
+        filtered_volumes, format_level, generate, select_airspace, volume_name, volume_type,
+    };
+    use crate::state::Settings;
+
+    #[test]
+    fn round_trip() {
+        let yaixm = fixture();
+        let mut settings = Settings {
+            radio: true,
+            colorize: true,
+            label_points: true,
+            include_notes: true,
+            max_level: 195,
+            rat: vec!["ROYAL WEDDING".to_string()],
+            ..Settings::default()
+        };
+        settings.loa.insert("CAMBRIDGE RAZ".to_string());
+        settings.wave.insert("EDEN WAVE".to_string());
+
+        let data = generate(&yaixm, &settings, "", now()).unwrap().data;
+        let parsed = parse_openair(&data).unwrap();
+
+        let (airspace, _) = select_airspace(&yaixm, &settings, now());
+        let expected = filtered_volumes(&airspace, &settings)
+            .map(|(feature, n, volume)| {
+                (
+                    volume_type(feature, &volume, &settings).to_string(),
+                    volume_name(feature, &volume, n, &settings),
+                    format_level(&volume.lower),
+                    format_level(&volume.upper),
+                )
+            })
+            .collect::<Vec<(String, String, String, String)>>();
+
+        let volumes = parsed
+            .iter()
+            .map(|x| {
+                (
+                    x.class.clone(),
+                    x.name.clone(),
+                    x.lower.clone(),
+                    x.upper.clone(),
+                )
+            })
+            .collect::<Vec<(String, String, String, String)>>();
+        assert_eq!(volumes, expected);
+        assert!(parsed.iter().all(|x| x.segments > 0));
+        assert!(parsed
+            .iter()
+            .any(|x| x.name == "EDEN WAVE" && x.upper == "FL195"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse_openair("AN NAME\n"), Err(ParseError::MissingClass(1)));
+        assert_eq!(
+            parse_openair("AC D\nAN NAME\nAL SFC\nAH FL65\n"),
+            Err(ParseError::Incomplete(4))
+        );
+        assert_eq!(
+            parse_openair("AC D\nXX 1\n"),
+            Err(ParseError::UnknownDirective(2))
+        );
+    }
+}
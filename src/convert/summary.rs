@@ -0,0 +1,206 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use super::{airfilter, build_airspace, class_airtype, header, volume_name, volume_type};
+use crate::state::{AirType, Format, Settings};
+use crate::yaixm::{Boundary, Feature, IcaoType, LocalType, Volume, Yaixm};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+// Approximate KML style and folder markup for each airspace type
+const KML_STYLE_BYTES: usize = 220;
+
+// Count of output airspace features by type, and approximate output size
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub counts: HashMap<AirType, usize>,
+    pub total: usize,
+    pub bytes: usize,
+}
+
+// Summarise airspace features the current settings will produce, counted
+// as for the generated output. Features are typed by their first volume
+pub fn summarize(settings: &Settings, source: &Yaixm, now: DateTime<Utc>) -> Summary {
+    let airspace = build_airspace(source, settings, now);
+
+    let mut summary = Summary {
+        bytes: header_bytes(settings, source, now),
+        ..Summary::default()
+    };
+    for feature in &airspace {
+        let volumes = feature
+            .geometry
+            .iter()
+            .enumerate()
+            .filter(|(_, volume)| airfilter(feature, volume, settings))
+            .collect::<Vec<(usize, &Volume)>>();
+
+        if let Some((_, volume)) = volumes.first() {
+            let air_type = class_airtype(volume_type(feature, volume, settings));
+            *summary.counts.entry(air_type).or_insert(0) += 1;
+            summary.total += 1;
+        }
+        summary.bytes += volumes
+            .iter()
+            .map(|(n, volume)| volume_bytes(feature, *n, volume, settings))
+            .sum::<usize>();
+    }
+
+    // KML has a style and folder for each type
+    if settings.format == Format::Kml {
+        summary.bytes += summary.counts.len() * KML_STYLE_BYTES;
+    }
+    summary
+}
+
+// Bytes before the first volume. OpenAir and TNP headers are built for real,
+// without the (unknown) user agent, the rest are fixed preambles
+fn header_bytes(settings: &Settings, source: &Yaixm, now: DateTime<Utc>) -> usize {
+    let rel = &source.release;
+    let header = |comment| {
+        header(
+            &rel.note,
+            &rel.airac_date,
+            &rel.commit,
+            "",
+            settings,
+            comment,
+            now,
+        )
+        .len()
+    };
+
+    match settings.format {
+        Format::Tnp => header("#"),
+        Format::GeoJson => 45,
+        Format::Kml => 150,
+        Format::Gpx => 140,
+        Format::Cup => 62,
+        Format::Csv => 52,
+        _ => header("*"),
+    }
+}
+
+// Approximate bytes written for a volume, from its name and number of
+// boundary points
+fn volume_bytes(feature: &Feature, n: usize, volume: &Volume, settings: &Settings) -> usize {
+    // Bytes per volume excluding name and boundary, bytes per boundary
+    // point, and whether arcs and circles are written as points
+    let (fixed, point, tessellate) = match settings.format {
+        // Feature, properties and geometry wrapper, [lon, lat] pairs at full
+        // precision
+        Format::GeoJson => (130, 40, true),
+        // Placemark, description, style and polygon markup, lon,lat,alt
+        // triples to six decimal places
+        Format::Kml => (330, 28, true),
+        // Waypoint, name and type elements for circular volumes only
+        Format::Gpx => match volume.boundary.as_slice() {
+            [Boundary::Circle(_)] => (90, 0, false),
+            _ => return 0,
+        },
+        // Row with coordinates for each gliding site
+        Format::Cup => {
+            if n == 0
+                && feature.icao_type == IcaoType::Other
+                && feature.local_type == Some(LocalType::Glider)
+            {
+                (50, 0, false)
+            } else {
+                return 0;
+            }
+        }
+        // Type and level columns
+        Format::Csv => (22, 0, false),
+        // INCLUDE, TYPE, CLASS, TITLE, TOPS and BASE lines, POINT lines
+        Format::Tnp => (60, 23, false),
+        // AC, AN, AL and AH lines, DP lines
+        _ => (26, 26, false),
+    };
+
+    // Arcs and circles are a couple of records unless tessellated, lines
+    // are closed by repeating the first point
+    let resolution = settings.arc_resolution as usize;
+    let points = volume
+        .boundary
+        .iter()
+        .map(|segment| match segment {
+            Boundary::Line(line) => line.len(),
+            Boundary::Arc(_) if tessellate => resolution / 4,
+            Boundary::Arc(_) => 3,
+            Boundary::Circle(_) if tessellate => resolution,
+            Boundary::Circle(_) => 2,
+        })
+        .sum::<usize>()
+        + 1;
+
+    fixed + volume_name(feature, volume, n, settings).len() + points * point
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::generate;
+    use crate::convert::tests::{fixture, now};
+
+    #[test]
+    fn feature_counts() {
+        let yaixm = fixture();
+        let settings = Settings::default();
+        let summary = summarize(&settings, &yaixm, now());
+
+        // Both SOLENT CTA volumes count as one feature
+        assert_eq!(summary.counts.get(&AirType::ClassD), Some(&2));
+        assert_eq!(summary.counts.get(&AirType::Danger), Some(&2));
+        assert_eq!(summary.counts.get(&AirType::ClassG), Some(&1));
+        assert_eq!(summary.counts.values().sum::<usize>(), summary.total);
+        assert_eq!(
+            summary.total,
+            generate(&yaixm, &settings, "", now()).unwrap().count
+        );
+    }
+
+    // Estimate within 20% of the real output size
+    #[test]
+    fn estimate_tolerance() {
+        let yaixm = fixture();
+        let mut settings = Settings {
+            gliding: Some(AirType::Gliding),
+            rat: vec!["ROYAL WEDDING".to_string()],
+            ..Settings::default()
+        };
+        settings.wave.insert("EDEN WAVE".to_string());
+
+        for format in [
+            Format::OpenAir,
+            Format::Tnp,
+            Format::GeoJson,
+            Format::Kml,
+            Format::Gpx,
+            Format::Cup,
+            Format::Csv,
+        ] {
+            settings.format = format;
+            let actual = generate(&yaixm, &settings, "", now()).unwrap().data.len();
+            let estimate = summarize(&settings, &yaixm, now()).bytes;
+            assert!(
+                estimate.abs_diff(actual) < actual / 5,
+                "{:?}: estimate {}, actual {}",
+                format,
+                estimate,
+                actual
+            );
+        }
+    }
+}
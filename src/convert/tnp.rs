@@ -0,0 +1,258 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use super::{format_distance, header, is_notam, volume_name, volume_type};
+use crate::altitude::{parse_altitude, Altitude, Reference, Unit};
+use crate::state::Settings;
+use crate::yaixm::{Arc, Boundary, Circle, Feature, Volume, Yaixm};
+use chrono::{DateTime, Utc};
+use std::borrow::Cow;
+
+// TNP type and optional class from OpenAir type
+fn tnp_type(openair_type: &str) -> (&'static str, Option<&'static str>) {
+    match openair_type {
+        "A" => ("CTA/CTR", Some("A")),
+        "B" => ("CTA/CTR", Some("B")),
+        "C" => ("CTA/CTR", Some("C")),
+        "D" => ("CTA/CTR", Some("D")),
+        "E" => ("CTA/CTR", Some("E")),
+        "F" => ("CTA/CTR", Some("F")),
+        "G" => ("CTA/CTR", Some("G")),
+        "CTA" | "CTR" => ("CTA/CTR", None),
+        "P" => ("PROHIBITED", None),
+        "Q" => ("DANGER", None),
+        "R" => ("RESTRICTED", None),
+        "W" => ("GSEC", None),
+        "MATZ" => ("MATZ", None),
+        "TMZ" => ("TMZ", None),
+        _ => ("OTHER", None),
+    }
+}
+
+// TNP level format
+fn format_level(level: &str) -> String {
+    match parse_altitude(level) {
+        Ok(alt) if alt.is_surface() => "SFC".to_string(),
+        Ok(alt) if alt.is_unlimited() => "UNLTD".to_string(),
+        Ok(Altitude {
+            value,
+            unit: Unit::FlightLevel,
+            ..
+        }) => format!("FL{}", value),
+        Ok(Altitude {
+            value,
+            reference: Reference::Agl,
+            ..
+        }) => format!("{}AGL", value),
+        Ok(Altitude { value, .. }) => format!("{}ALT", value),
+        Err(_) => level.to_string(),
+    }
+}
+
+// TNP lat/lon format
+fn format_latlon(latlon: &str) -> String {
+    format!(
+        "{}{} {}{}",
+        &latlon[6..7],
+        &latlon[..6],
+        &latlon[15..16],
+        &latlon[8..15]
+    )
+}
+
+fn do_point(point: &str) -> String {
+    format!("POINT={}\n", format_latlon(point))
+}
+
+fn do_circle(circle: &Circle) -> String {
+    format!(
+        "CIRCLE RADIUS={} CENTRE={}\n",
+        format_distance(&circle.radius),
+        format_latlon(&circle.centre)
+    )
+}
+
+fn do_arc(arc: &Arc) -> String {
+    let dir = if arc.dir == "cw" {
+        "CLOCKWISE"
+    } else {
+        "ANTI-CLOCKWISE"
+    };
+
+    format!(
+        "{} RADIUS={} CENTRE={} TO={}\n",
+        dir,
+        format_distance(&arc.radius),
+        format_latlon(&arc.centre),
+        format_latlon(&arc.to)
+    )
+}
+
+fn do_boundary(boundary: &[Boundary]) -> String {
+    let mut out = String::new();
+
+    for segment in boundary {
+        match segment {
+            Boundary::Line(line) => {
+                for point in line {
+                    out.push_str(&do_point(point));
+                }
+            }
+            Boundary::Arc(arc) => out.push_str(&do_arc(arc)),
+            Boundary::Circle(circle) => out.push_str(&do_circle(circle)),
+        }
+    }
+
+    out
+}
+
+// Generate TNP data, with header timestamp
+pub fn tnp<'a>(
+    yaixm: &Yaixm,
+    volumes: impl IntoIterator<Item = (&'a Feature, usize, Cow<'a, Volume>)>,
+    settings: &Settings,
+    user_agent: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let rel = &yaixm.release;
+    let mut output = header(
+        &rel.note,
+        &rel.airac_date,
+        &rel.commit,
+        user_agent,
+        settings,
+        "#",
+        now,
+    );
+    for (feature, n, volume) in volumes {
+        // NOTAM airspace uses OpenAir class G, but isn't class G
+        // controlled airspace
+        let (tnp_type, tnp_class) = if is_notam(feature, &volume) {
+            ("OTHER", None)
+        } else {
+            tnp_type(volume_type(feature, &volume, settings))
+        };
+
+        output.push_str("#\n");
+        output.push_str("INCLUDE=YES\n");
+        output.push_str(&format!("TYPE={}\n", tnp_type));
+        if let Some(class) = tnp_class {
+            output.push_str(&format!("CLASS={}\n", class));
+        }
+        output.push_str(&format!(
+            "TITLE={}\n",
+            volume_name(feature, &volume, n, settings)
+        ));
+        output.push_str(&format!("TOPS={}\n", format_level(&volume.upper)));
+        output.push_str(&format!("BASE={}\n", format_level(&volume.lower)));
+        if settings.radio {
+            if let Some(freq) = volume.frequency {
+                output.push_str(&format!("RADIO={:.3}\n", freq));
+            }
+        }
+        output.push_str(&do_boundary(&volume.boundary));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::generate;
+    use crate::convert::tests::{fixture, now};
+    use crate::state::Format;
+
+    // Output block for the named volume
+    fn block(output: &str, title: &str) -> String {
+        output
+            .split("#\n")
+            .find(|block| block.contains(&format!("TITLE={}\n", title)))
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    fn tnp_fixture() {
+        let settings = Settings {
+            radio: true,
+            max_level: 100,
+            format: Format::Tnp,
+            ..Settings::default()
+        };
+        let output = generate(&fixture(), &settings, "", now()).unwrap().data;
+
+        assert_eq!(
+            block(&output, "BRISTOL CTR 125.650"),
+            "INCLUDE=YES\n\
+            TYPE=CTA/CTR\n\
+            CLASS=D\n\
+            TITLE=BRISTOL CTR 125.650\n\
+            TOPS=FL65\n\
+            BASE=SFC\n\
+            RADIO=125.650\n\
+            POINT=N512000 W0024000\n\
+            POINT=N512000 W0024030\n\
+            POINT=N512030 W0024030\n\
+            POINT=N512030 W0024000\n"
+        );
+        assert_eq!(
+            block(&output, "D123 IMBER"),
+            "INCLUDE=YES\n\
+            TYPE=DANGER\n\
+            TITLE=D123 IMBER\n\
+            TOPS=FL100\n\
+            BASE=SFC\n\
+            CIRCLE RADIUS=2 CENTRE=N512000 W0010000\n"
+        );
+        assert_eq!(
+            block(&output, "SOLENT CTA"),
+            "INCLUDE=YES\n\
+            TYPE=CTA/CTR\n\
+            CLASS=D\n\
+            TITLE=SOLENT CTA\n\
+            TOPS=3500ALT\n\
+            BASE=2000ALT\n\
+            POINT=N505000 W0012000\n\
+            POINT=N505000 W0012030\n\
+            POINT=N505030 W0012030\n\
+            POINT=N505030 W0012000\n"
+        );
+
+        // Wave box base is above the maximum level
+        assert!(!output.contains("EDEN WAVE"));
+    }
+
+    #[test]
+    fn tnp_levels() {
+        assert_eq!(format_level("GND"), "SFC");
+        assert_eq!(format_level("UNL"), "UNLTD");
+        assert_eq!(format_level("FL195"), "FL195");
+        assert_eq!(format_level("2000 ft amsl"), "2000ALT");
+        assert_eq!(format_level("1500 ft agl"), "1500AGL");
+    }
+
+    #[test]
+    fn tnp_notam() {
+        let settings = Settings {
+            format: Format::Tnp,
+            ..Settings::default()
+        };
+        let output = generate(&fixture(), &settings, "", now()).unwrap().data;
+
+        let notam = block(&output, "ROYAL INTERNATIONAL AIR TATTOO (NOTAM)");
+        assert!(notam.contains("TYPE=OTHER\n"));
+        assert!(!notam.contains("CLASS="));
+    }
+}
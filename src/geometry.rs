@@ -0,0 +1,338 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::yaixm::Boundary;
+use std::f64::consts::PI;
+
+// Default number of segments in a full circle
+pub const ARC_RESOLUTION: u16 = 64;
+
+// Point as (latitude, longitude) in degrees
+pub type LatLon = (f64, f64);
+
+// Earth radius in nautical miles
+const EARTH_RADIUS: f64 = 3440.065;
+
+// Convert "DDMMSSN DDDMMSSW" to decimal degrees
+pub fn parse_latlon(latlon: &str) -> LatLon {
+    let dms = |deg: &str, min: &str, sec: &str| {
+        deg.parse::<f64>().unwrap_or(0.0)
+            + min.parse::<f64>().unwrap_or(0.0) / 60.0
+            + sec.parse::<f64>().unwrap_or(0.0) / 3600.0
+    };
+
+    let lat = dms(&latlon[..2], &latlon[2..4], &latlon[4..6]);
+    let lon = dms(&latlon[8..11], &latlon[11..13], &latlon[13..15]);
+
+    (
+        if &latlon[6..7] == "S" { -lat } else { lat },
+        if &latlon[15..16] == "W" { -lon } else { lon },
+    )
+}
+
+// Convert distance, e.g. "2 nm" or "5 km", to nautical miles
+pub fn parse_distance(distance: &str) -> f64 {
+    match distance.split_once(' ') {
+        Some((dist, "km")) => dist.parse::<f64>().unwrap_or(0.0) / 1.852,
+        Some((dist, _)) => dist.parse::<f64>().unwrap_or(0.0),
+        None => 0.0,
+    }
+}
+
+// Initial great circle bearing (radians) and distance (nm) between points
+pub fn bearing_distance(from: LatLon, to: LatLon) -> (f64, f64) {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let dlon = lon2 - lon1;
+
+    let bearing = (dlon.sin() * lat2.cos())
+        .atan2(lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos());
+
+    let a =
+        ((lat2 - lat1) / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    let distance = 2.0 * a.sqrt().asin() * EARTH_RADIUS;
+
+    (bearing, distance)
+}
+
+// Destination point given start, bearing (radians) and distance (nm)
+pub fn destination(from: LatLon, bearing: f64, distance: f64) -> LatLon {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let d = distance / EARTH_RADIUS;
+
+    let lat2 = (lat1.sin() * d.cos() + lat1.cos() * d.sin() * bearing.cos()).asin();
+    let lon2 =
+        lon1 + (bearing.sin() * d.sin() * lat1.cos()).atan2(d.cos() - lat1.sin() * lat2.sin());
+
+    (lat2.to_degrees(), lon2.to_degrees())
+}
+
+// Tessellate arc from start to end bearing, including both end points
+fn arc_points(
+    centre: LatLon,
+    radius: f64,
+    start: f64,
+    end: f64,
+    clockwise: bool,
+    resolution: u16,
+) -> Vec<LatLon> {
+    let mut sweep = if clockwise { end - start } else { start - end };
+    if sweep <= 0.0 {
+        sweep += 2.0 * PI;
+    }
+
+    let n = ((sweep / (2.0 * PI) * resolution as f64).ceil() as usize).max(1);
+    let step = if clockwise { sweep } else { -sweep } / n as f64;
+
+    (0..=n)
+        .map(|i| destination(centre, start + step * i as f64, radius))
+        .collect()
+}
+
+// Closed polygon ring from boundary segments
+pub fn boundary_ring(boundary: &[Boundary], resolution: u16) -> Vec<LatLon> {
+    let mut ring: Vec<LatLon> = Vec::new();
+
+    for segment in boundary {
+        match segment {
+            Boundary::Line(line) => {
+                ring.extend(line.iter().map(|p| parse_latlon(p)));
+            }
+            Boundary::Arc(arc) => {
+                let centre = parse_latlon(&arc.centre);
+                let to = parse_latlon(&arc.to);
+                let radius = parse_distance(&arc.radius);
+
+                let start = match ring.last() {
+                    Some(&from) => bearing_distance(centre, from).0,
+                    None => bearing_distance(centre, to).0,
+                };
+                let end = bearing_distance(centre, to).0;
+
+                let points = arc_points(centre, radius, start, end, arc.dir == "cw", resolution);
+
+                // Arc starts at the previous point, and ends exactly on the next
+                ring.extend(&points[1..points.len() - 1]);
+                ring.push(to);
+            }
+            Boundary::Circle(circle) => {
+                let centre = parse_latlon(&circle.centre);
+                let radius = parse_distance(&circle.radius);
+
+                ring.extend(
+                    (0..resolution).map(|i| {
+                        destination(centre, 2.0 * PI * i as f64 / resolution as f64, radius)
+                    }),
+                );
+            }
+        }
+    }
+
+    // Close the ring
+    if let (Some(&first), Some(&last)) = (ring.first(), ring.last()) {
+        if first != last {
+            ring.push(first);
+        }
+    }
+
+    ring
+}
+
+// Local flat projection in nm, relative to origin
+fn project(origin: LatLon, point: LatLon) -> (f64, f64) {
+    let x = (point.1 - origin.1) * 60.0 * origin.0.to_radians().cos();
+    let y = (point.0 - origin.0) * 60.0;
+    (x, y)
+}
+
+// Area weighted centroid of ring, or average of the points if the ring
+// has no area
+pub fn centroid(ring: &[LatLon]) -> Option<LatLon> {
+    let origin = *ring.first()?;
+
+    let (mut area, mut cx, mut cy) = (0.0, 0.0, 0.0);
+    for edge in ring.windows(2) {
+        let (ax, ay) = project(origin, edge[0]);
+        let (bx, by) = project(origin, edge[1]);
+        let cross = ax * by - bx * ay;
+        area += cross;
+        cx += (ax + bx) * cross;
+        cy += (ay + by) * cross;
+    }
+
+    if area.abs() < 1e-9 {
+        let n = ring.len() as f64;
+        let (lat, lon) = ring
+            .iter()
+            .fold((0.0, 0.0), |acc, p| (acc.0 + p.0, acc.1 + p.1));
+        return Some((lat / n, lon / n));
+    }
+
+    let (x, y) = (cx / (3.0 * area), cy / (3.0 * area));
+    Some((
+        origin.0 + y / 60.0,
+        origin.1 + x / (60.0 * origin.0.to_radians().cos()),
+    ))
+}
+
+// Test if point is inside ring
+fn contains(ring: &[LatLon], point: LatLon) -> bool {
+    let mut inside = false;
+    for edge in ring.windows(2) {
+        let (a, b) = (edge[0], edge[1]);
+        if (a.0 > point.0) != (b.0 > point.0)
+            && point.1 < (b.1 - a.1) * (point.0 - a.0) / (b.0 - a.0) + a.1
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// Test if line segments ab and cd cross
+fn segments_cross(a: LatLon, b: LatLon, c: LatLon, d: LatLon) -> bool {
+    let orient =
+        |p: LatLon, q: LatLon, r: LatLon| (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0);
+
+    ((orient(c, d, a) > 0.0) != (orient(c, d, b) > 0.0))
+        && ((orient(a, b, c) > 0.0) != (orient(a, b, d) > 0.0))
+}
+
+// Test if ring intersects box given as (min_lat, min_lon, max_lat, max_lon)
+pub fn intersects_box(ring: &[LatLon], bbox: (f64, f64, f64, f64)) -> bool {
+    let (min_lat, min_lon, max_lat, max_lon) = bbox;
+
+    let in_box = |p: &LatLon| p.0 >= min_lat && p.0 <= max_lat && p.1 >= min_lon && p.1 <= max_lon;
+    if ring.iter().any(in_box) {
+        return true;
+    }
+
+    // Box inside ring
+    let corners = [
+        (min_lat, min_lon),
+        (min_lat, max_lon),
+        (max_lat, max_lon),
+        (max_lat, min_lon),
+        (min_lat, min_lon),
+    ];
+    if contains(ring, corners[0]) {
+        return true;
+    }
+
+    ring.windows(2).any(|edge| {
+        corners
+            .windows(2)
+            .any(|side| segments_cross(edge[0], edge[1], side[0], side[1]))
+    })
+}
+
+// Test if ring intersects circle of given radius (nm)
+pub fn intersects_circle(ring: &[LatLon], centre: LatLon, radius: f64) -> bool {
+    if contains(ring, centre) {
+        return true;
+    }
+
+    ring.windows(2).any(|edge| {
+        let (ax, ay) = project(centre, edge[0]);
+        let (bx, by) = project(centre, edge[1]);
+
+        // Closest point on edge to centre
+        let (dx, dy) = (bx - ax, by - ay);
+        let len2 = dx * dx + dy * dy;
+        let t = if len2 > 0.0 {
+            (-(ax * dx + ay * dy) / len2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let (px, py) = (ax + t * dx, ay + t * dy);
+        (px * px + py * py).sqrt() <= radius
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    const CENTRE: LatLon = (51.0, -1.0);
+
+    // Square ring around CENTRE, with half side in degrees
+    fn square(half: f64) -> Vec<LatLon> {
+        vec![
+            (CENTRE.0 - half, CENTRE.1 - half),
+            (CENTRE.0 - half, CENTRE.1 + half),
+            (CENTRE.0 + half, CENTRE.1 + half),
+            (CENTRE.0 + half, CENTRE.1 - half),
+            (CENTRE.0 - half, CENTRE.1 - half),
+        ]
+    }
+
+    #[test]
+    fn arc_resolution() {
+        // Full circle, both end points included
+        assert_eq!(arc_points(CENTRE, 5.0, 0.0, 0.0, true, 8).len(), 9);
+        assert_eq!(arc_points(CENTRE, 5.0, 0.0, 0.0, true, 64).len(), 65);
+
+        // Quarter circle, either direction
+        let points = arc_points(CENTRE, 5.0, 0.0, FRAC_PI_2, true, 64);
+        assert_eq!(points.len(), 17);
+        assert_eq!(arc_points(CENTRE, 5.0, FRAC_PI_2, 0.0, false, 64).len(), 17);
+
+        // Points lie on the arc, from start to end bearing
+        for point in &points {
+            assert!((bearing_distance(CENTRE, *point).1 - 5.0).abs() < 1e-6);
+        }
+        assert!(bearing_distance(CENTRE, points[0]).0.abs() < 1e-6);
+        assert!((bearing_distance(CENTRE, points[16]).0 - FRAC_PI_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn centroid_point() {
+        let (lat, lon) = centroid(&square(0.1)).unwrap();
+        assert!((lat - CENTRE.0).abs() < 1e-3);
+        assert!((lon - CENTRE.1).abs() < 1e-3);
+
+        // Degenerate ring is the average of its points
+        assert_eq!(centroid(&[(50.0, -1.0), (52.0, -1.0)]), Some((51.0, -1.0)));
+        assert_eq!(centroid(&[]), None);
+    }
+
+    #[test]
+    fn box_intersection() {
+        let ring = square(0.1);
+
+        // Ring point inside box
+        assert!(intersects_box(&ring, (50.8, -1.2, 50.95, -0.95)));
+        // Box inside ring
+        assert!(intersects_box(&ring, (50.98, -1.02, 51.02, -0.98)));
+        // Edges crossing, no corners inside either
+        assert!(intersects_box(&ring, (50.95, -1.5, 51.05, -0.5)));
+        // Disjoint
+        assert!(!intersects_box(&ring, (52.0, -1.2, 52.5, -0.8)));
+    }
+
+    #[test]
+    fn circle_intersection() {
+        let ring = square(0.1);
+
+        // Centre inside ring
+        assert!(intersects_circle(&ring, CENTRE, 1.0));
+        // Ring edge 6 nm north of centre
+        let north = (CENTRE.0 + 0.2, CENTRE.1);
+        assert!(intersects_circle(&ring, north, 6.5));
+        assert!(!intersects_circle(&ring, north, 5.5));
+    }
+}
@@ -13,27 +13,51 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
+use chrono::Utc;
 use futures::try_join;
 use gloo::file::{Blob, ObjectUrl};
 use gloo::net::{http::Request, Error};
-use gloo::storage::{LocalStorage, Storage};
+use std::collections::HashSet;
+use std::rc::Rc;
 use yew::{
-    classes, function_component, html, use_effect_with, use_node_ref, use_reducer, use_state,
-    AttrValue, Callback, Html,
+    classes, function_component, html, use_effect_with, use_memo, use_node_ref, use_reducer,
+    use_state, AttrValue, Callback, Html, Reducible,
 };
 
 use components::{
     about_tab::AboutTab, airspace_tab::AirspaceTab, extra_panel::ExtraPanel, extra_tab::ExtraTab,
-    notam_tab::NotamTab, options_tab::OptionsTab, tabs::Tabs,
+    notam_tab::NotamTab, options_tab::OptionsTab, profile_tab::ProfileTab, tabs::Tabs,
 };
-use convert::openair;
-use state::{Action, State};
-use yaixm::{gliding_sites, loa_names, rat_names, wave_names, Yaixm};
+use convert::{
+    custom_overlay, extension, generate, generate_bundle, generate_preview, generate_split,
+    is_openair, line_endings, summary::summarize,
+};
+use state::{diff, valid_release, Action, AirType, Format, Settings, State};
+use yaixm::{danger_names, gliding_sites, loa_categories, loa_names, rat_names, wave_names, Yaixm};
 
+mod altitude;
+mod compact;
 mod components;
 mod convert;
+mod geometry;
 mod state;
+mod storage;
 mod yaixm;
+mod zip;
+
+// Formats included in the combined download
+const BUNDLE_FORMATS: [Format; 2] = [Format::OpenAir, Format::Cup];
+
+// Number of volumes in the OpenAir preview
+const PREVIEW_VOLUMES: usize = 20;
+
+// Quick setup for gliding
+const GLIDER_SETUP: [(&str, &str); 4] = [
+    ("atz", "ctr"),
+    ("gliding", "gsec"),
+    ("max_level", "195"),
+    ("format", "openair"),
+];
 
 // Callback data structures
 pub struct AirspaceSetting {
@@ -46,6 +70,7 @@ pub enum ExtraCategory {
     Rat,
     Loa,
     Wave,
+    Exclude,
 }
 
 pub struct ExtraSetting {
@@ -54,6 +79,42 @@ pub struct ExtraSetting {
     pub checked: bool,
 }
 
+pub struct MoveSetting {
+    pub name: String,
+    pub to_index: usize,
+}
+
+pub struct GroupSetting {
+    pub group: String,
+    pub checked: bool,
+    pub members: Vec<String>,
+}
+
+pub struct RangeSetting {
+    pub prefix: String,
+    pub checked: bool,
+}
+
+pub struct SiteSetting {
+    pub name: String,
+    pub checked: bool,
+}
+
+pub struct OverlaySetting {
+    pub name: String,
+    pub checked: bool,
+}
+
+pub struct TypeSetting {
+    pub air_type: AirType,
+    pub enabled: bool,
+}
+
+pub struct TypeCapSetting {
+    pub air_type: AirType,
+    pub level: Option<u16>,
+}
+
 #[derive(Default)]
 pub struct Overlay {
     pub overlay_105: String,
@@ -61,6 +122,22 @@ pub struct Overlay {
     pub overlay_atzdz: String,
 }
 
+impl Overlay {
+    // Selected overlay data, in a fixed order
+    fn selected(&self, yaixm: &Yaixm, settings: &Settings) -> String {
+        let mut overlays = settings.overlay.iter().collect::<Vec<_>>();
+        overlays.sort();
+        overlays
+            .into_iter()
+            .fold(String::new(), |output, selected| match selected {
+                state::Overlay::FL195 => output + &self.overlay_195,
+                state::Overlay::FL105 => output + &self.overlay_105,
+                state::Overlay::AtzDz => output + &self.overlay_atzdz,
+                state::Overlay::Custom(level) => output + &custom_overlay(yaixm, *level),
+            })
+    }
+}
+
 #[function_component]
 fn App() -> Html {
     // Airspace data
@@ -69,27 +146,128 @@ fn App() -> Html {
     // Overlay data
     let overlay = use_state(Overlay::default);
 
-    // User interface settings
-    let state = use_reducer(|| State {
-        settings: LocalStorage::get("settings").unwrap_or_default(),
+    // User interface settings, from shared link or local storage
+    let state = use_reducer(|| {
+        let query = web_sys::window()
+            .and_then(|w| w.location().search().ok())
+            .unwrap_or_default();
+
+        let saved_settings = storage::load_settings();
+        let state = State {
+            settings: saved_settings.clone(),
+            saved_settings: saved_settings.clone(),
+            profiles: storage::load_profiles(),
+            ..Default::default()
+        };
+        if query.is_empty() {
+            return state;
+        }
+
+        // Shared link options, then its LOA/RAT/Wave selections replacing
+        // the saved ones, each as an undo step
+        let link = Settings::from_query_string(&query);
+        let state = Rc::new(State {
+            settings: Settings {
+                loa: saved_settings.loa.clone(),
+                rat: saved_settings.rat.clone(),
+                wave: saved_settings.wave.clone(),
+                ..link.clone()
+            },
+            link_changes: diff(&saved_settings, &link),
+            ..state
+        });
+        let state = [
+            Action::ReplaceLoa { names: link.loa },
+            Action::ReplaceRat { names: link.rat },
+            Action::ReplaceWave { names: link.wave },
+        ]
+        .into_iter()
+        .fold(state, |state, action| state.reduce(action));
+
+        (*state).clone()
     });
 
+    // Feature counts and output size, only recalculated when settings or data change
+    let summary = {
+        let yaixm = yaixm.clone();
+        use_memo(
+            (state.settings.clone(), yaixm.is_some()),
+            move |(settings, _)| {
+                yaixm
+                    .as_ref()
+                    .map(|yaixm| summarize(settings, yaixm, Utc::now()))
+            },
+        )
+    };
+
+    // OpenAir preview of the lowest airspace, when shown
+    let show_preview = use_state(|| false);
+    let preview = {
+        let yaixm = yaixm.clone();
+        use_memo(
+            (state.settings.clone(), yaixm.is_some(), *show_preview),
+            move |(settings, _, show)| {
+                yaixm
+                    .as_ref()
+                    .filter(|_| *show)
+                    .map(|yaixm| generate_preview(settings, yaixm, PREVIEW_VOLUMES, Utc::now()))
+            },
+        )
+    };
+
     // Release modal control
     let show_release = use_state(|| false);
 
-    // Reference for download anchor element
+    // Reference for download anchor elements
     let anchor_node_ref = use_node_ref();
+    let settings_anchor_node_ref = use_node_ref();
+    let session_anchor_node_ref = use_node_ref();
+    let bundle_anchor_node_ref = use_node_ref();
+
+    // Save settings in local storage whenever they change
+    {
+        let state = state.clone();
+        use_effect_with(state.settings.clone(), move |settings| {
+            if state.is_dirty() {
+                storage::save_settings(settings);
+                state.dispatch(Action::MarkSaved);
+            }
+            || ()
+        });
+    }
+
+    use_effect_with(state.profiles.clone(), |profiles| {
+        storage::save_profiles(profiles);
+        || ()
+    });
 
     // Fetch YAIXM and overlay data
     {
         let yaixm = yaixm.clone();
         let overlay = overlay.clone();
+        let state = state.clone();
 
         // use_effect_with((), ...) triggers only on first render of component
         use_effect_with((), move |_| {
             wasm_bindgen_futures::spawn_local(async move {
                 // Get YAIXM data (and trigger page render)
-                yaixm.set(fetch_yaixm().await.ok());
+                let data = fetch_yaixm(state.settings.data_release.as_deref())
+                    .await
+                    .ok();
+
+                // Drop stale LOA/RAT/Wave names from saved settings
+                if let Some(data) = &data {
+                    state.dispatch(Action::SetLoadedRelease {
+                        airac_date: data.release.airac_date.clone(),
+                        commit: data.release.commit.clone(),
+                    });
+                    state.dispatch(Action::RenameActiveSelection {
+                        loa: loa_names(data),
+                        rat: rat_names(data),
+                        wave: wave_names(data),
+                    });
+                }
+                yaixm.set(data);
 
                 // Get overlay data
                 let overlay_105 = fetch_overlay("overlay_105.txt");
@@ -114,25 +292,78 @@ fn App() -> Html {
         let yaixm = yaixm.clone();
         let state = state.clone();
         let anchor_node_ref = anchor_node_ref.clone();
+        let bundle_anchor_node_ref = bundle_anchor_node_ref.clone();
 
         let user_agent = web_sys::window()
             .and_then(|w| w.navigator().user_agent().ok())
             .unwrap_or_default();
 
         Callback::from(move |_| {
-            // Save settings in local storage
-            let _ = LocalStorage::set("settings", &state.settings);
-
-            // Create OpenAir data
-            let oa = openair(yaixm.as_ref().unwrap(), &state.settings, &user_agent);
-            let overlay = match state.settings.overlay {
-                Some(state::Overlay::FL195) => overlay.overlay_195.as_str(),
-                Some(state::Overlay::FL105) => overlay.overlay_105.as_str(),
-                Some(state::Overlay::AtzDz) => overlay.overlay_atzdz.as_str(),
-                None => "",
+            // Create airspace data
+            let yaixm = yaixm.as_ref().unwrap();
+
+            // One file per airspace type, plus any overlays, downloaded as
+            // a ZIP archive
+            if state.settings.split_by_type && is_openair(state.settings.format) {
+                let stem = format!("uk{}", &yaixm.release.airac_date[..10]);
+                let (mut entries, truncated) =
+                    match generate_split(yaixm, &state.settings, &stem, &user_agent, Utc::now()) {
+                        Ok(split) => split,
+                        Err(err) => {
+                            gloo::dialogs::alert(&format!("Can't create airspace: {}", err));
+                            return;
+                        }
+                    };
+                if truncated {
+                    gloo::dialogs::alert(&format!(
+                        "Feature limit exceeded, only the lowest {} features are included",
+                        state.settings.max_features.unwrap_or_default()
+                    ));
+                }
+
+                let overlay_data = overlay.selected(yaixm, &state.settings);
+                if !overlay_data.is_empty() {
+                    entries.push((
+                        format!("{}_overlay.txt", stem),
+                        line_endings(&overlay_data, &state.settings),
+                    ));
+                }
+
+                let blob =
+                    Blob::new_with_options(zip::zip(&entries).as_slice(), Some("application/zip"));
+                let object_url = ObjectUrl::from(blob);
+
+                let anchor_node_ref = bundle_anchor_node_ref.cast::<web_sys::HtmlAnchorElement>();
+                if let Some(anchor_node_ref) = anchor_node_ref {
+                    anchor_node_ref.set_href(&object_url);
+                    anchor_node_ref.click();
+                }
+                return;
+            }
+
+            let generated = match generate(yaixm, &state.settings, &user_agent, Utc::now()) {
+                Ok(generated) => generated,
+                Err(err) => {
+                    gloo::dialogs::alert(&format!("Can't create airspace: {}", err));
+                    return;
+                }
             };
+            if generated.truncated {
+                gloo::dialogs::alert(&format!(
+                    "Feature limit exceeded, only the lowest {} features are included",
+                    generated.count
+                ));
+            }
+            let data = generated.data;
 
-            let blob = Blob::new((oa + overlay).as_str());
+            let data = if is_openair(state.settings.format) {
+                data + &overlay.selected(yaixm, &state.settings)
+            } else {
+                data
+            };
+            let data = line_endings(&data, &state.settings);
+
+            let blob = Blob::new(data.as_str());
             let object_url = ObjectUrl::from(blob);
 
             // Trigger a "fake" download
@@ -144,7 +375,86 @@ fn App() -> Html {
         })
     };
 
+    // Save airspace bundle callback
+    let onsave_bundle = {
+        let yaixm = yaixm.clone();
+        let state = state.clone();
+        let bundle_anchor_node_ref = bundle_anchor_node_ref.clone();
+
+        let user_agent = web_sys::window()
+            .and_then(|w| w.navigator().user_agent().ok())
+            .unwrap_or_default();
+
+        Callback::from(move |_| {
+            let yaixm = yaixm.as_ref().unwrap();
+            let stem = format!("uk{}", &yaixm.release.airac_date[..10]);
+            let entries = match generate_bundle(
+                yaixm,
+                &state.settings,
+                &BUNDLE_FORMATS,
+                &stem,
+                &user_agent,
+                Utc::now(),
+            ) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    gloo::dialogs::alert(&format!("Can't create airspace: {}", err));
+                    return;
+                }
+            };
+
+            let blob =
+                Blob::new_with_options(zip::zip(&entries).as_slice(), Some("application/zip"));
+            let object_url = ObjectUrl::from(blob);
+
+            let anchor_node_ref = bundle_anchor_node_ref.cast::<web_sys::HtmlAnchorElement>();
+            if let Some(anchor_node_ref) = anchor_node_ref {
+                anchor_node_ref.set_href(&object_url);
+                anchor_node_ref.click();
+            }
+        })
+    };
+
+    // Save settings callback
+    let onsave_settings = {
+        let state = state.clone();
+        let settings_anchor_node_ref = settings_anchor_node_ref.clone();
+
+        Callback::from(move |_| {
+            let blob = Blob::new(state.settings.to_pretty_json().as_str());
+            let object_url = ObjectUrl::from(blob);
+
+            let anchor_node_ref = settings_anchor_node_ref.cast::<web_sys::HtmlAnchorElement>();
+            if let Some(anchor_node_ref) = anchor_node_ref {
+                anchor_node_ref.set_href(&object_url);
+                anchor_node_ref.click();
+            }
+        })
+    };
+
+    // Save session callback
+    let onsave_session = {
+        let state = state.clone();
+        let session_anchor_node_ref = session_anchor_node_ref.clone();
+
+        Callback::from(move |_| {
+            let blob = Blob::new(state.to_session_json().as_str());
+            let object_url = ObjectUrl::from(blob);
+
+            let anchor_node_ref = session_anchor_node_ref.cast::<web_sys::HtmlAnchorElement>();
+            if let Some(anchor_node_ref) = anchor_node_ref {
+                anchor_node_ref.set_href(&object_url);
+                anchor_node_ref.click();
+            }
+        })
+    };
+
     // Release modal callbacks
+    let ontoggle_preview = {
+        let show_preview = show_preview.clone();
+        Callback::from(move |_| show_preview.set(!*show_preview))
+    };
+
     let onshow_release = {
         let show_release = show_release.clone();
         Callback::from(move |_| {
@@ -163,14 +473,99 @@ fn App() -> Html {
     let onairspace_set = {
         let state = state.clone();
         Callback::from(move |setting: AirspaceSetting| {
-            state.dispatch(Action::Set {
+            if setting.name == "home_coords" {
+                if setting.value.trim().is_empty() {
+                    state.dispatch(Action::ClearHomeCoords)
+                } else if let Some((lat, lon)) = state::parse_coords(&setting.value) {
+                    state.dispatch(Action::SetHomeCoords { lat, lon })
+                }
+            } else {
+                state.dispatch(Action::Set {
+                    name: setting.name,
+                    value: setting.value,
+                })
+            }
+        })
+    };
+
+    // Gliding site checkbox callbacks
+    let onsite_set = {
+        let state = state.clone();
+        Callback::from(move |setting: SiteSetting| {
+            state.dispatch(Action::SetGlidingSite {
+                name: setting.name,
+                checked: setting.checked,
+            })
+        })
+    };
+
+    let onsites_clear = {
+        let state = state.clone();
+        Callback::from(move |_| state.dispatch(Action::ClearGlidingSites))
+    };
+
+    // Format select callback
+    let onformat_set = {
+        let state = state.clone();
+        Callback::from(move |format: Format| state.dispatch(Action::SetFormat(format)))
+    };
+
+    // Maximum level select callback
+    let onmax_level_set = {
+        let state = state.clone();
+        Callback::from(move |preset| state.dispatch(Action::SetMaxLevelPreset(preset)))
+    };
+
+    // Overlay checkbox callbacks
+    let onoverlay_set = {
+        let state = state.clone();
+        Callback::from(move |setting: OverlaySetting| {
+            state.dispatch(Action::SetOverlay {
                 name: setting.name,
-                value: setting.value,
+                checked: setting.checked,
+            })
+        })
+    };
+
+    let onoverlay_clear = {
+        let state = state.clone();
+        Callback::from(move |_| state.dispatch(Action::ClearOverlay))
+    };
+
+    // Radio frequency checkbox callback
+    let onradio_set = {
+        let state = state.clone();
+        Callback::from(move |radio: bool| state.dispatch(Action::SetRadio(radio)))
+    };
+
+    let onradio_toggle = {
+        let state = state.clone();
+        Callback::from(move |_| state.dispatch(Action::ToggleRadio))
+    };
+
+    // Airspace type checkbox callback
+    let ontype_set = {
+        let state = state.clone();
+        Callback::from(move |setting: TypeSetting| {
+            state.dispatch(Action::SetTypeEnabled {
+                air_type: setting.air_type,
+                enabled: setting.enabled,
+            })
+        })
+    };
+
+    // Airspace type level cap callback
+    let ontype_cap_set = {
+        let state = state.clone();
+        Callback::from(move |setting: TypeCapSetting| {
+            state.dispatch(Action::SetTypeCap {
+                air_type: setting.air_type,
+                level: setting.level,
             })
         })
     };
 
-    // RAT/LOA/Wave setting callback
+    // RAT/LOA/Wave/Exclude setting callback
     let onextra_set = {
         let state = state.clone();
         Callback::from(move |setting: ExtraSetting| match setting.category {
@@ -186,31 +581,136 @@ fn App() -> Html {
                 name: setting.name,
                 checked: setting.checked,
             }),
+            ExtraCategory::Exclude => state.dispatch(Action::SetExclude {
+                name: setting.name,
+                checked: setting.checked,
+            }),
         })
     };
 
-    // RAT/LOA/Wave clear callback
+    // RAT output order callback
+    let onrat_move = {
+        let state = state.clone();
+        Callback::from(move |setting: MoveSetting| {
+            state.dispatch(Action::MoveRat {
+                name: setting.name,
+                to_index: setting.to_index,
+            })
+        })
+    };
+
+    // RAT/LOA/Wave/Exclude clear callback
     let onextra_clear = {
         let state = state.clone();
         Callback::from(move |category: ExtraCategory| match category {
             ExtraCategory::Rat => state.dispatch(Action::ClearRat),
             ExtraCategory::Loa => state.dispatch(Action::ClearLoa),
             ExtraCategory::Wave => state.dispatch(Action::ClearWave),
+            ExtraCategory::Exclude => state.dispatch(Action::ClearExclude),
         })
     };
 
+    let onextra_clear_all = {
+        let state = state.clone();
+        Callback::from(move |_| state.dispatch(Action::ClearAll))
+    };
+
+    // Profile callbacks
+    let onprofile_save = {
+        let state = state.clone();
+        Callback::from(move |name: String| state.dispatch(Action::SaveProfile { name }))
+    };
+
+    let onprofile_load = {
+        let state = state.clone();
+        Callback::from(move |name: String| state.dispatch(Action::LoadProfile { name }))
+    };
+
+    // Import settings callback
+    let onprofile_import = {
+        let state = state.clone();
+        Callback::from(move |text: String| state.dispatch(Action::ImportSettings { text }))
+    };
+
+    // Merge settings callback
+    let onprofile_merge = {
+        let state = state.clone();
+        Callback::from(move |text: String| state.dispatch(Action::MergeSettings { text }))
+    };
+
+    // Restore session callback
+    let onprofile_restore = {
+        let state = state.clone();
+        Callback::from(move |text: String| state.dispatch(Action::RestoreSession { text }))
+    };
+
+    // Keep option when merging callback
+    let onprofile_touch = {
+        let state = state.clone();
+        Callback::from(move |name: String| state.dispatch(Action::TouchSetting { name }))
+    };
+
+    // Glider quick setup callback
+    let onglider_setup = {
+        let state = state.clone();
+        Callback::from(move |_| {
+            state.dispatch(Action::SetMany {
+                entries: GLIDER_SETUP
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+            })
+        })
+    };
+
+    // Reset settings callback
+    let onreset = {
+        let state = state.clone();
+        Callback::from(move |_| state.dispatch(Action::Reset))
+    };
+
+    // Undo/redo callbacks
+    let onundo = {
+        let state = state.clone();
+        Callback::from(move |_| state.dispatch(Action::Undo))
+    };
+
+    let onredo = {
+        let state = state.clone();
+        Callback::from(move |_| state.dispatch(Action::Redo))
+    };
+
     // HTML rendering
     match yaixm.as_ref() {
         // Render full interface if YAIXM data is available
         Some(yaixm) => {
             let airac_date = &yaixm.release.airac_date[..10];
             let release_note = &yaixm.release.note;
-            let filename = format!("uk{}.txt", airac_date);
+            let filename = format!("uk{}.{}", airac_date, extension(state.settings.format));
+            let bundle_filename = format!("uk{}.zip", airac_date);
+            let share_link = format!("?{}", state.settings.to_query_string());
+            let warnings = state.settings.validate();
+            let overlay_hint = state.settings.overlay_conflicts();
+
+            // Feature counts for current settings
+            let summary = (*summary).clone().unwrap_or_default();
+            let mut summary_counts = summary.counts.iter().collect::<Vec<_>>();
+            summary_counts.sort();
+            let summary_counts = summary_counts
+                .into_iter()
+                .map(|(air_type, count)| format!("{:?}: {}", air_type, count))
+                .collect::<Vec<String>>();
+            let size_kb = summary.bytes.div_ceil(1000);
 
             let mut gliding_sites = gliding_sites(yaixm);
             gliding_sites.sort();
 
-            let rat_selected = state.settings.rat.clone();
+            let rat_selected = state
+                .settings
+                .rat
+                .iter()
+                .cloned()
+                .collect::<HashSet<String>>();
             let rat_names = rat_names(yaixm);
 
             let loa_selected = state.settings.loa.clone();
@@ -220,16 +720,102 @@ fn App() -> Html {
             let mut wave_names = wave_names(yaixm);
             wave_names.sort();
 
+            let exclude_selected = state.settings.exclude.clone();
+            let mut danger_names = danger_names(yaixm);
+            danger_names.sort();
+
+            let mut touched = state.touched.iter().cloned().collect::<Vec<String>>();
+            touched.sort();
+
+            let profile_names = state
+                .profiles
+                .iter()
+                .map(|p| p.name.clone())
+                .collect::<Vec<String>>();
+
+            // RAT/LOA/Wave/Exclude select all callback
+            let onextra_select_all = {
+                let state = state.clone();
+                let rat_names = rat_names.clone();
+                let loa_names = loa_names.clone();
+                let wave_names = wave_names.clone();
+                let danger_names = danger_names.clone();
+                Callback::from(move |category: ExtraCategory| match category {
+                    ExtraCategory::Rat => state.dispatch(Action::SelectAllRat {
+                        names: rat_names.clone(),
+                    }),
+                    ExtraCategory::Loa => state.dispatch(Action::SelectAllLoa {
+                        names: loa_names.clone(),
+                    }),
+                    ExtraCategory::Wave => state.dispatch(Action::SelectAllWave {
+                        names: wave_names.clone(),
+                    }),
+                    ExtraCategory::Exclude => state.dispatch(Action::SelectAllExclude {
+                        names: danger_names.clone(),
+                    }),
+                })
+            };
+
+            // RAT/LOA/Wave/Exclude invert callback
+            let onextra_invert = {
+                let state = state.clone();
+                let rat_names = rat_names.clone();
+                let loa_names = loa_names.clone();
+                let wave_names = wave_names.clone();
+                let danger_names = danger_names.clone();
+                Callback::from(move |category: ExtraCategory| match category {
+                    ExtraCategory::Rat => state.dispatch(Action::InvertRat {
+                        all: rat_names.clone(),
+                    }),
+                    ExtraCategory::Loa => state.dispatch(Action::InvertLoa {
+                        all: loa_names.clone(),
+                    }),
+                    ExtraCategory::Wave => state.dispatch(Action::InvertWave {
+                        all: wave_names.clone(),
+                    }),
+                    ExtraCategory::Exclude => state.dispatch(Action::InvertExclude {
+                        all: danger_names.clone(),
+                    }),
+                })
+            };
+
+            // LOA category callback
+            let onloa_category = {
+                let state = state.clone();
+                Callback::from(move |setting: GroupSetting| {
+                    state.dispatch(Action::SetLoaCategory {
+                        category: setting.group,
+                        checked: setting.checked,
+                        members: setting.members,
+                    })
+                })
+            };
+
+            // Wave box prefix select/clear callback
+            let onwave_range = {
+                let state = state.clone();
+                let wave_names = wave_names.clone();
+                Callback::from(move |setting: RangeSetting| {
+                    state.dispatch(Action::SetWaveRange {
+                        prefix: setting.prefix,
+                        checked: setting.checked,
+                        all: wave_names.clone(),
+                    })
+                })
+            };
+
             let extra_names = vec![
                 AttrValue::Static("Temporary Restrictions"),
                 AttrValue::Static("Local Agreements"),
                 AttrValue::Static("Wave Boxes"),
+                AttrValue::Static("Exclude Danger Areas"),
             ];
 
             let tab_names = vec![
                 AttrValue::Static("Main"),
                 AttrValue::Static("Option"),
                 AttrValue::Static("Extra"),
+                AttrValue::Static("Profile"),
                 AttrValue::Static("NOTAM"),
                 AttrValue::Static("About"),
             ];
@@ -248,29 +834,111 @@ fn App() -> Html {
 
                 <div class="container block">
                   <Tabs {tab_names}>
-                    <AirspaceTab settings={state.settings.clone()} {gliding_sites} callback={onairspace_set.clone()} />
-                    <OptionsTab settings={state.settings.clone()} callback={onairspace_set.clone()} />
-                    <ExtraTab names={extra_names} categories={vec![ExtraCategory::Rat, ExtraCategory::Loa, ExtraCategory::Wave]} on_clear={onextra_clear.clone()}>
-                      <ExtraPanel category={ExtraCategory::Rat} names={rat_names} selected={rat_selected} callback={onextra_set.clone()}/>
-                      <ExtraPanel category={ExtraCategory::Loa} names={loa_names} selected={loa_selected} callback={onextra_set.clone()}/>
-                      <ExtraPanel category={ExtraCategory::Wave} names={wave_names} selected={wave_selected} callback={onextra_set.clone()}/>
+                    <AirspaceTab settings={state.settings.clone()} {gliding_sites} callback={onairspace_set.clone()} site_callback={onsite_set} clear_sites_callback={onsites_clear} />
+                    <OptionsTab settings={state.settings.clone()} callback={onairspace_set.clone()} overlay_callback={onoverlay_set} clear_overlay_callback={onoverlay_clear} format_callback={onformat_set} max_level_callback={onmax_level_set} radio_callback={onradio_set} type_callback={ontype_set} type_cap_callback={ontype_cap_set} />
+                    <ExtraTab names={extra_names} categories={vec![ExtraCategory::Rat, ExtraCategory::Loa, ExtraCategory::Wave, ExtraCategory::Exclude]} on_clear={onextra_clear.clone()} on_clear_all={onextra_clear_all} on_invert={onextra_invert} on_select_all={onextra_select_all} selection={state.settings.selected_names()}>
+                      <ExtraPanel category={ExtraCategory::Rat} names={rat_names} selected={rat_selected} callback={onextra_set.clone()} order={state.settings.rat.clone()} move_callback={onrat_move}/>
+                      <ExtraPanel category={ExtraCategory::Loa} names={loa_names} selected={loa_selected} callback={onextra_set.clone()} groups={loa_categories(yaixm)} group_callback={onloa_category}/>
+                      <ExtraPanel category={ExtraCategory::Wave} names={wave_names} selected={wave_selected} callback={onextra_set.clone()} range_callback={onwave_range}/>
+                      <ExtraPanel category={ExtraCategory::Exclude} names={danger_names} selected={exclude_selected} callback={onextra_set.clone()}/>
                     </ExtraTab>
+                    <ProfileTab names={profile_names} active={state.active_profile()} on_save={onprofile_save} on_load={onprofile_load} on_import={onprofile_import} on_merge={onprofile_merge} on_restore={onprofile_restore} touched={touched} on_touch={onprofile_touch} import_error={state.import_error.clone()} code={state.settings.to_base64()} />
                     <NotamTab />
                     <AboutTab />
                   </Tabs>
                 </div>
 
+                if !warnings.is_empty() {
+                  <div class="container block">
+                    <div class="notification is-warning mx-4">
+                      { for warnings.iter().map(|w| html! { <p>{ w.to_string() }</p> }) }
+                    </div>
+                  </div>
+                }
+
+                if state.release_fallback() {
+                  <div class="container block">
+                    <div class="notification is-warning mx-4">
+                      {"Pinned data release isn't available, using the latest airspace data"}
+                    </div>
+                  </div>
+                }
+
+                if !state.link_changes.is_empty() {
+                  <div class="container block">
+                    <div class="notification is-info mx-4">
+                      <details>
+                        <summary>{"Shared link settings differ from your saved settings"}</summary>
+                        <ul>
+                          { for state.link_changes.iter().map(|change| html! { <li>{ change.to_string() }</li> }) }
+                        </ul>
+                      </details>
+                    </div>
+                  </div>
+                }
+
+                if let Some(hint) = overlay_hint {
+                  <div class="container block">
+                    <div class="notification is-info mx-4">{ hint }</div>
+                  </div>
+                }
+
                 <div class="container block">
                   <div class="mx-4">
                     <button class="button is-primary" onclick={onsave}>
                       {"Get Airspace"}
                     </button>
+                    <button class="button is-text" onclick={onsave_bundle} title="OpenAir and CUP, without overlays">
+                      {"Bundle"}
+                    </button>
+                    <button class="button is-text" onclick={ontoggle_preview} title="Lowest airspace in OpenAir format">
+                      {if *show_preview { "Hide Preview" } else { "Preview" }}
+                    </button>
+                    <button class="button is-text" onclick={onundo} disabled={state.undo.is_empty()}>
+                      {"Undo"}
+                    </button>
+                    <button class="button is-text" onclick={onredo} disabled={state.redo.is_empty()}>
+                      {"Redo"}
+                    </button>
+                    <button class="button is-text" onclick={onradio_toggle} title="Add radio frequencies to names">
+                      {if state.settings.radio { "Radio On" } else { "Radio Off" }}
+                    </button>
+                    <button class="button is-text" onclick={onglider_setup} title="CTR as ATZ, gliding sectors, FL195, OpenAir">
+                      {"Glider Setup"}
+                    </button>
+                    <button class="button is-text" onclick={onreset}>
+                      {"Reset"}
+                    </button>
+                    <a class="button is-text" href={share_link}>
+                      {"Link"}
+                    </a>
+                    <button class="button is-text" onclick={onsave_settings}>
+                      {"Settings"}
+                    </button>
+                    <button class="button is-text" onclick={onsave_session} title="Settings, undo history and profiles">
+                      {"Session"}
+                    </button>
+                    <span class="button is-static is-white" title={summary_counts.join("\n")}>
+                      {summary.total}{" features, ~"}{size_kb}{" kB"}
+                    </span>
                     <a id="airac-button" class="button is-text is-pulled-right" onclick={onshow_release}>
                     {"AIRAC: "}{ airac_date }
                     </a>
                   </div>
                 </div>
 
+                <div class="container block">
+                  <p class="mx-4 has-text-grey" aria-live="polite">{ state.settings.describe() }</p>
+                </div>
+
+                if let Some(preview) = (*preview).clone() {
+                  <div class="container block">
+                    <div class="mx-4">
+                      <textarea class="textarea is-family-monospace is-small" rows="16" readonly=true value={preview}/>
+                    </div>
+                  </div>
+                }
+
                 <div class={classes!("modal", show_release.then(|| Some("is-active")))}>
                   <div class="modal-background"></div>
                   <div class="modal-content">
@@ -283,6 +951,9 @@ fn App() -> Html {
                 </div>
 
                 <a ref={anchor_node_ref} id="download" hidden=true download={filename}></a>
+                <a ref={settings_anchor_node_ref} hidden=true download="settings.json"></a>
+                <a ref={session_anchor_node_ref} hidden=true download="session.json"></a>
+                <a ref={bundle_anchor_node_ref} hidden=true download={bundle_filename}></a>
                 </>
             }
         }
@@ -295,8 +966,21 @@ fn App() -> Html {
     }
 }
 
-// Get YAIXM data from server
-async fn fetch_yaixm() -> Result<Yaixm, Error> {
+// Get YAIXM data from server, the pinned release if available, otherwise
+// the latest
+async fn fetch_yaixm(release: Option<&str>) -> Result<Yaixm, Error> {
+    // Only well formed release tags are used in the path
+    if let Some(release) = release.filter(|release| valid_release(release)) {
+        if let Ok(response) = Request::get(&format!("yaixm_{}.json", release))
+            .send()
+            .await
+        {
+            if response.ok() {
+                return response.json().await;
+            }
+        }
+    }
+
     let result = Request::get("yaixm.json").send().await;
     match result {
         Ok(response) => response.json().await,
@@ -319,6 +1003,27 @@ async fn fetch_overlay(path: &str) -> Result<String, Error> {
     }
 }
 
+// Log messages (debug builds only) go to the browser console
+struct ConsoleLogger;
+
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        gloo::console::debug!(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: ConsoleLogger = ConsoleLogger;
+
 fn main() {
+    if cfg!(debug_assertions) && log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Debug);
+    }
+
     yew::Renderer::<App>::new().render();
 }
@@ -13,14 +13,19 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 //
-use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use crate::geometry::ARC_RESOLUTION;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::rc::Rc;
 use yew::Reducible;
 
 // Airspace types
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum AirType {
+    Awy,
     ClassA,
     ClassB,
     ClassC,
@@ -40,166 +45,4028 @@ pub enum AirType {
     Tmz,
 }
 
+impl AirType {
+    // All types, in sort order
+    pub const ALL: [AirType; 18] = [
+        AirType::ClassA,
+        AirType::ClassB,
+        AirType::ClassC,
+        AirType::ClassD,
+        AirType::ClassE,
+        AirType::Ctr,
+        AirType::Cta,
+        AirType::Awy,
+        AirType::Prohibited,
+        AirType::Restricted,
+        AirType::Danger,
+        AirType::Matz,
+        AirType::Tmz,
+        AirType::Rmz,
+        AirType::ClassF,
+        AirType::ClassG,
+        AirType::Gliding,
+        AirType::Other,
+    ];
+
+    // Ordering from controlled airspace, through restrictions and advisory
+    // zones, to everything else
+    pub fn sort_key(self) -> u8 {
+        match self {
+            AirType::ClassA => 0,
+            AirType::ClassB => 1,
+            AirType::ClassC => 2,
+            AirType::ClassD => 3,
+            AirType::ClassE => 4,
+            AirType::Ctr => 5,
+            AirType::Cta => 6,
+            AirType::Awy => 7,
+            AirType::Prohibited => 8,
+            AirType::Restricted => 9,
+            AirType::Danger => 10,
+            AirType::Matz => 11,
+            AirType::Tmz => 12,
+            AirType::Rmz => 13,
+            AirType::ClassF => 14,
+            AirType::ClassG => 15,
+            AirType::Gliding => 16,
+            AirType::Other => 17,
+        }
+    }
+}
+
+impl Ord for AirType {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl PartialOrd for AirType {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 // Output format
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Format {
     OpenAir,
     RatOnly,
     Competition,
+    Tnp,
+    GeoJson,
+    Gpx,
+    Cup,
+    Kml,
+    Csv,
+    OpenAirLk,
+}
+
+// Common maximum level choices
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MaxLevelPreset {
+    Everything,
+    Glider,
+    Fl125,
+    Low,
+    Fl65,
+}
+
+impl MaxLevelPreset {
+    pub const ALL: [MaxLevelPreset; 5] = [
+        MaxLevelPreset::Everything,
+        MaxLevelPreset::Glider,
+        MaxLevelPreset::Fl125,
+        MaxLevelPreset::Low,
+        MaxLevelPreset::Fl65,
+    ];
+
+    // Flight level for preset
+    pub fn level(self) -> u16 {
+        match self {
+            MaxLevelPreset::Everything => 660,
+            MaxLevelPreset::Glider => 195,
+            MaxLevelPreset::Fl125 => 125,
+            MaxLevelPreset::Low => 105,
+            MaxLevelPreset::Fl65 => 65,
+        }
+    }
+
+    // User interface label
+    pub fn label(self) -> &'static str {
+        match self {
+            MaxLevelPreset::Everything => "Unlimited",
+            MaxLevelPreset::Glider => "FL195",
+            MaxLevelPreset::Fl125 => "FL125",
+            MaxLevelPreset::Low => "FL105",
+            MaxLevelPreset::Fl65 => "FL65",
+        }
+    }
+}
+
+// Altitude reference notation for output levels. Without terrain data
+// levels can't be converted between references, so PreferAgl only writes
+// SFC as 0 ft AGL and PreferAmsl only marks altitudes as AMSL
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum AltitudeRef {
+    #[default]
+    AsIs,
+    PreferAgl,
+    PreferAmsl,
+}
+
+// Wave box handling where the maximum level cuts through it
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum WaveClip {
+    Drop,
+    #[default]
+    Clip,
+    Keep,
 }
 
 // Altutude layer overlay
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum Overlay {
     FL195,
     FL105,
     AtzDz,
+    Custom(u16),
+}
+
+// Current settings schema version
+pub const SETTINGS_VERSION: u32 = 1;
+
+fn current_version() -> u32 {
+    SETTINGS_VERSION
+}
+
+// Set as a sorted array, so saved settings are stable
+fn serialize_sorted<S: Serializer, T: Ord + Serialize>(
+    names: &HashSet<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut names = names.iter().collect::<Vec<&T>>();
+    names.sort();
+    names.serialize(serializer)
+}
+
+// Map with sorted keys, as for sets above
+fn serialize_sorted_map<S: Serializer, K: Ord + Serialize, V: Serialize>(
+    map: &HashMap<K, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    map.iter()
+        .collect::<BTreeMap<&K, &V>>()
+        .serialize(serializer)
+}
+
+fn default_matz() -> Option<AirType> {
+    Some(AirType::Matz)
+}
+
+fn default_freq_decimals() -> u8 {
+    3
+}
+
+fn default_arc_resolution() -> u16 {
+    ARC_RESOLUTION
 }
 
 // Settings
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Settings {
+    #[serde(default = "current_version")]
+    pub version: u32,
     pub atz: AirType,
     pub ils: Option<AirType>,
+    #[serde(default = "default_matz")]
+    pub matz: Option<AirType>,
     pub unlicensed: Option<AirType>,
     pub microlight: Option<AirType>,
     pub gliding: Option<AirType>,
     pub home: Option<String>,
+    #[serde(default)]
+    pub home_radius_nm: Option<u16>,
+    #[serde(default)]
+    pub home_coords: Option<(f64, f64)>,
+    #[serde(default)]
+    pub atz_buffer_nm: Option<f64>,
+    #[serde(default)]
+    pub competition_center: Option<String>,
+    #[serde(default)]
+    pub competition_radius_nm: Option<u16>,
+    #[serde(default)]
+    pub bbox: Option<(f64, f64, f64, f64)>,
     pub hirta_gvs: Option<AirType>,
     pub obstacle: Option<AirType>,
+    #[serde(default)]
+    pub obstacle_min_ft: Option<u16>,
+    #[serde(default)]
+    pub wave_min_fl: Option<u16>,
     pub max_level: u16,
+    #[serde(default)]
+    pub min_level: u16,
     pub radio: bool,
+    #[serde(default)]
+    pub radio_as_comment: bool,
+    #[serde(default)]
+    pub colorize: bool,
+    #[serde(default)]
+    pub low_only: bool,
+    #[serde(default)]
+    pub altitude_ref: AltitudeRef,
+    #[serde(default)]
+    pub wave_clip: WaveClip,
+    #[serde(default)]
+    pub name_prefix: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub data_release: Option<String>,
+    #[serde(default)]
+    pub active_now: bool,
+    #[serde(default)]
+    pub crlf: bool,
+    #[serde(default)]
+    pub dedupe_names: bool,
+    #[serde(default)]
+    pub split_by_type: bool,
+    #[serde(default = "default_freq_decimals")]
+    pub freq_decimals: u8,
+    #[serde(default)]
+    pub include_notes: bool,
+    #[serde(default)]
+    pub require_frequency: bool,
+    #[serde(default)]
+    pub label_points: bool,
+    #[serde(default)]
+    pub exclude_inactive_danger: bool,
+    #[serde(default = "default_arc_resolution")]
+    pub arc_resolution: u16,
+    #[serde(default)]
+    pub max_features: Option<usize>,
+    #[serde(default, serialize_with = "serialize_sorted_map")]
+    pub types: HashMap<AirType, bool>,
+    #[serde(default, serialize_with = "serialize_sorted_map")]
+    pub type_caps: HashMap<AirType, u16>,
     pub format: Format,
-    pub overlay: Option<Overlay>,
+    #[serde(default, serialize_with = "serialize_sorted")]
+    pub overlay: HashSet<Overlay>,
     #[serde(default)]
+    pub rat_from: Option<String>,
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub loa: HashSet<String>,
     #[serde(default)]
-    pub rat: HashSet<String>,
-    #[serde(default)]
+    pub rat: Vec<String>,
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub wave: HashSet<String>,
+    #[serde(default, serialize_with = "serialize_sorted")]
+    pub exclude: HashSet<String>,
+    #[serde(default, serialize_with = "serialize_sorted")]
+    pub gliding_sites: HashSet<String>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
+            version: SETTINGS_VERSION,
             atz: AirType::Ctr,
             ils: None,
+            matz: default_matz(),
             unlicensed: None,
             microlight: None,
             gliding: None,
             home: None,
+            home_radius_nm: None,
+            home_coords: None,
+            atz_buffer_nm: None,
+            competition_center: None,
+            competition_radius_nm: None,
+            bbox: None,
             hirta_gvs: None,
             obstacle: None,
+            obstacle_min_ft: None,
+            wave_min_fl: None,
             max_level: 660,
+            min_level: 0,
             radio: false,
+            radio_as_comment: false,
+            colorize: false,
+            low_only: false,
+            altitude_ref: AltitudeRef::AsIs,
+            wave_clip: WaveClip::Clip,
+            name_prefix: None,
+            author: None,
+            data_release: None,
+            active_now: false,
+            crlf: false,
+            dedupe_names: false,
+            split_by_type: false,
+            freq_decimals: default_freq_decimals(),
+            include_notes: false,
+            require_frequency: false,
+            label_points: false,
+            exclude_inactive_danger: false,
+            arc_resolution: default_arc_resolution(),
+            max_features: None,
+            types: HashMap::new(),
+            type_caps: HashMap::new(),
             format: Format::OpenAir,
-            overlay: None,
+            overlay: HashSet::new(),
+            rat_from: None,
             loa: HashSet::new(),
-            rat: HashSet::new(),
+            rat: Vec::new(),
             wave: HashSet::new(),
+            exclude: HashSet::new(),
+            gliding_sites: HashSet::new(),
         }
     }
 }
 
-// Application state
-#[derive(Debug, Default, PartialEq)]
-pub struct State {
-    pub settings: Settings,
-}
+impl Settings {
+    // Encode settings as URL query string, using the same name/value
+    // pairs as Action::Set. Options at their default value are left out
+    pub fn to_query_string(&self) -> String {
+        let defaults = Settings::default().to_full_query_string();
+        let defaults = form_urlencoded::parse(defaults.as_bytes()).collect::<HashSet<_>>();
 
-// State actions
-pub enum Action {
-    Set { name: String, value: String },
-    SetLoa { name: String, checked: bool },
-    SetRat { name: String, checked: bool },
-    SetWave { name: String, checked: bool },
-    ClearLoa,
-    ClearRat,
-    ClearWave,
-}
+        let full = self.to_full_query_string();
+        form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(form_urlencoded::parse(full.as_bytes()).filter(|x| !defaults.contains(x)))
+            .finish()
+    }
 
-impl Reducible for State {
-    type Action = Action;
+    // Encode all settings as URL query string
+    pub fn to_full_query_string(&self) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
 
-    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
-        let mut set = self.settings.clone();
-        match action {
-            // Set airspace option
-            Action::Set { name, value } => {
-                match name.as_str() {
-                    "atz" => set.atz = get_airtype(&value).unwrap_or(AirType::Ctr),
-                    "ils" => set.ils = get_airtype(&value),
-                    "unlicensed" => set.unlicensed = get_airtype(&value),
-                    "microlight" => set.microlight = get_airtype(&value),
-                    "gliding" => set.gliding = get_airtype(&value),
-                    "hirta_gvs" => set.hirta_gvs = get_airtype(&value),
-                    "obstacle" => set.obstacle = get_airtype(&value),
-                    "max_level" => set.max_level = value.parse::<u16>().unwrap(),
-                    "radio" => set.radio = value == "yes",
-                    "home" => set.home = if value == "no" { None } else { Some(value) },
-                    "overlay" => {
-                        set.overlay = match value.as_str() {
-                            "fl195" => Some(Overlay::FL195),
-                            "fl105" => Some(Overlay::FL105),
-                            "atzdz" => Some(Overlay::AtzDz),
-                            _ => None,
-                        }
-                    }
-                    "format" => {
-                        set.format = match value.as_str() {
-                            "ratonly" => Format::RatOnly,
-                            "competition" => Format::Competition,
-                            _ => Format::OpenAir,
-                        }
-                    }
-                    _ => (),
-                };
+        query.append_pair("atz", airtype_value(self.atz));
+        query.append_pair("matz", self.matz.map_or("exclude", airtype_value));
+        for (name, value) in [
+            ("ils", self.ils),
+            ("unlicensed", self.unlicensed),
+            ("microlight", self.microlight),
+            ("gliding", self.gliding),
+            ("hirta_gvs", self.hirta_gvs),
+            ("obstacle", self.obstacle),
+        ] {
+            if let Some(air_type) = value {
+                query.append_pair(name, airtype_value(air_type));
             }
-            // Include/exclude LOA
-            Action::SetLoa { name, checked } => {
-                if checked {
-                    set.loa.replace(name);
-                } else {
-                    set.loa.remove(&name);
-                }
+        }
+
+        if let Some(home) = &self.home {
+            query.append_pair("home", home);
+        }
+        if let Some((lat, lon)) = self.home_coords {
+            query.append_pair("home_coords", &format!("{},{}", lat, lon));
+        }
+        if let Some((min_lat, min_lon, max_lat, max_lon)) = self.bbox {
+            query.append_pair(
+                "bbox",
+                &format!("{},{},{},{}", min_lat, min_lon, max_lat, max_lon),
+            );
+        }
+        if let Some(radius) = self.home_radius_nm {
+            query.append_pair("home_radius", &radius.to_string());
+        }
+        if let Some(buffer) = self.atz_buffer_nm {
+            query.append_pair("atz_buffer", &buffer.to_string());
+        }
+        if let Some(center) = &self.competition_center {
+            query.append_pair("competition_center", center);
+        }
+        if let Some(radius) = self.competition_radius_nm {
+            query.append_pair("competition_radius", &radius.to_string());
+        }
+        if let Some(height) = self.obstacle_min_ft {
+            query.append_pair("obstacle_min_ft", &height.to_string());
+        }
+        if let Some(level) = self.wave_min_fl {
+            query.append_pair("wave_min_fl", &level.to_string());
+        }
+        query.append_pair("max_level", &self.max_level.to_string());
+        query.append_pair("min_level", &self.min_level.to_string());
+        query.append_pair("radio", if self.radio { "yes" } else { "no" });
+        query.append_pair(
+            "radio_as_comment",
+            if self.radio_as_comment { "yes" } else { "no" },
+        );
+        query.append_pair("colorize", if self.colorize { "yes" } else { "no" });
+        query.append_pair("low_only", if self.low_only { "yes" } else { "no" });
+        query.append_pair("active_now", if self.active_now { "yes" } else { "no" });
+        query.append_pair("crlf", if self.crlf { "yes" } else { "no" });
+        query.append_pair("dedupe_names", if self.dedupe_names { "yes" } else { "no" });
+        query.append_pair(
+            "split_by_type",
+            if self.split_by_type { "yes" } else { "no" },
+        );
+        query.append_pair("freq_decimals", &self.freq_decimals.to_string());
+        query.append_pair("arc_resolution", &self.arc_resolution.to_string());
+        query.append_pair(
+            "include_notes",
+            if self.include_notes { "yes" } else { "no" },
+        );
+        query.append_pair(
+            "require_frequency",
+            if self.require_frequency { "yes" } else { "no" },
+        );
+        query.append_pair("label_points", if self.label_points { "yes" } else { "no" });
+        query.append_pair(
+            "exclude_inactive_danger",
+            if self.exclude_inactive_danger {
+                "yes"
+            } else {
+                "no"
+            },
+        );
+        query.append_pair(
+            "altitude_ref",
+            match self.altitude_ref {
+                AltitudeRef::AsIs => "asis",
+                AltitudeRef::PreferAgl => "agl",
+                AltitudeRef::PreferAmsl => "amsl",
+            },
+        );
+        query.append_pair(
+            "wave_clip",
+            match self.wave_clip {
+                WaveClip::Drop => "drop",
+                WaveClip::Clip => "clip",
+                WaveClip::Keep => "keep",
+            },
+        );
+        if let Some(prefix) = &self.name_prefix {
+            query.append_pair("name_prefix", prefix);
+        }
+        if let Some(author) = &self.author {
+            query.append_pair("author", author);
+        }
+        if let Some(release) = &self.data_release {
+            query.append_pair("data_release", release);
+        }
+        if let Some(date) = &self.rat_from {
+            query.append_pair("rat_from", date);
+        }
+        if let Some(max_features) = self.max_features {
+            query.append_pair("max_features", &max_features.to_string());
+        }
+        query.append_pair("format", format_value(self.format));
+        let mut overlays = self.overlay.iter().collect::<Vec<&Overlay>>();
+        overlays.sort();
+        for overlay in overlays {
+            query.append_pair("overlay", &overlay_name(overlay));
+        }
+
+        // Disabled airspace types
+        let mut disabled = self
+            .types
+            .iter()
+            .filter(|(_, enabled)| !**enabled)
+            .map(|(air_type, _)| airtype_value(*air_type))
+            .collect::<Vec<&str>>();
+        disabled.sort();
+        for value in disabled {
+            query.append_pair("disable", value);
+        }
+
+        // Per airspace type level caps
+        let mut caps = self
+            .type_caps
+            .iter()
+            .map(|(air_type, level)| format!("{}:{}", airtype_value(*air_type), level))
+            .collect::<Vec<String>>();
+        caps.sort();
+        for value in caps {
+            query.append_pair("cap", &value);
+        }
+
+        // Repeated keys for LOA/Wave/Exclude, sorted to give a stable link.
+        // RATs are in output order
+        for rat in &self.rat {
+            query.append_pair("rat", rat);
+        }
+        for (name, values) in [
+            ("loa", &self.loa),
+            ("wave", &self.wave),
+            ("exclude", &self.exclude),
+            ("gliding_site", &self.gliding_sites),
+        ] {
+            let mut values = values.iter().collect::<Vec<&String>>();
+            values.sort();
+            for value in values {
+                query.append_pair(name, value);
             }
-            // Include/exclude RAT
-            Action::SetRat { name, checked } => {
-                if checked {
-                    set.rat.replace(name);
-                } else {
-                    set.rat.remove(&name);
+        }
+
+        query.finish()
+    }
+
+    // Settings as JSON. Name sets serialize sorted so output is stable,
+    // RATs stay in output order
+    pub fn to_pretty_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    // Set option from name/value pair, as Action::Set but reporting unknown
+    // names and unparseable values rather than falling back to a default
+    pub fn try_apply(&mut self, name: &str, value: &str) -> Result<(), SettingsError> {
+        let valid = match name {
+            "atz" => get_airtype_strict(value).is_ok(),
+            "ils" => value == "atz" || parse_optional_airtype(value).is_some(),
+            "matz" | "unlicensed" | "microlight" | "gliding" | "hirta_gvs" | "obstacle" => {
+                parse_optional_airtype(value).is_some()
+            }
+            "home" | "name_prefix" | "author" | "competition_center" => true,
+            "data_release" => {
+                let release = value.trim();
+                release.is_empty() || release == "latest" || valid_release(release)
+            }
+            "home_coords" => value.is_empty() || value == "no" || parse_coords(value).is_some(),
+            "bbox" => value.is_empty() || value == "no" || parse_bbox(value).is_some(),
+            "home_radius" | "competition_radius" | "obstacle_min_ft" => {
+                value == "no" || value.parse::<u16>().is_ok()
+            }
+            "max_features" => value == "no" || value.parse::<usize>().is_ok(),
+            "atz_buffer" => value == "no" || parse_atz_buffer(value).is_some(),
+            "freq_decimals" => value == "2" || value == "3",
+            "arc_resolution" => value.parse::<u16>().is_ok(),
+            "max_level" | "min_level" => parse_level(value).is_some(),
+            "wave_min_fl" => value == "no" || parse_level(value).is_some(),
+            "radio"
+            | "radio_as_comment"
+            | "colorize"
+            | "low_only"
+            | "active_now"
+            | "crlf"
+            | "dedupe_names"
+            | "split_by_type"
+            | "include_notes"
+            | "require_frequency"
+            | "label_points"
+            | "exclude_inactive_danger" => parse_bool(value).is_some(),
+            "rat_from" => value.is_empty() || value == "no" || parse_date(value).is_some(),
+            "overlay" => value == "no" || parse_overlay(value).is_some(),
+            "format" => parse_format(value).is_some(),
+            "disable" => get_airtype_strict(value).is_ok(),
+            "cap" => parse_type_cap(value).is_some(),
+            "altitude_ref" => parse_altitude_ref(value).is_some(),
+            "wave_clip" => parse_wave_clip(value).is_some(),
+            _ => return Err(SettingsError::UnknownName(name.to_string())),
+        };
+
+        if valid {
+            apply_set(self, name, value);
+            Ok(())
+        } else {
+            Err(SettingsError::InvalidValue {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+        }
+    }
+
+    // Decode settings from URL query string. Unknown names and invalid
+    // values are ignored, and missing values are left as default
+    pub fn from_query_string(query: &str) -> Settings {
+        let query = query.strip_prefix('?').unwrap_or(query);
+
+        let mut settings = Settings::default();
+        for (name, value) in form_urlencoded::parse(query.as_bytes()) {
+            match name.as_ref() {
+                "loa" => {
+                    settings.loa.insert(value.into_owned());
+                }
+                "rat" => {
+                    if !settings.rat.contains(&value.to_string()) {
+                        settings.rat.push(value.into_owned());
+                    }
+                }
+                "wave" => {
+                    settings.wave.insert(value.into_owned());
+                }
+                "exclude" => {
+                    settings.exclude.insert(value.into_owned());
+                }
+                "gliding_site" => {
+                    settings.gliding_sites.insert(value.into_owned());
+                }
+                _ => {
+                    let _ = settings.try_apply(&name, &value);
                 }
             }
-            // Include/exclude wave box
-            Action::SetWave { name, checked } => {
-                if checked {
-                    set.wave.replace(name);
-                } else {
-                    set.wave.remove(&name);
+        }
+
+        settings
+    }
+
+    // All selected LOA, RAT and Wave box names, sorted by kind then name
+    pub fn selected_names(&self) -> Vec<(SelectionKind, String)> {
+        let rat = self.rat.iter().cloned().collect::<HashSet<String>>();
+        let mut names = [
+            (SelectionKind::Loa, &self.loa),
+            (SelectionKind::Rat, &rat),
+            (SelectionKind::Wave, &self.wave),
+        ]
+        .into_iter()
+        .flat_map(|(kind, names)| names.iter().map(move |name| (kind, name.clone())))
+        .collect::<Vec<(SelectionKind, String)>>();
+
+        names.sort();
+        names
+    }
+
+    // Non-fatal warnings for implausible airspace type assignments
+    pub fn validate(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        for (name, value, plausible) in [
+            (
+                "ils",
+                self.ils,
+                &[
+                    AirType::ClassD,
+                    AirType::ClassF,
+                    AirType::ClassG,
+                    AirType::Ctr,
+                ][..],
+            ),
+            (
+                "unlicensed",
+                self.unlicensed,
+                &[AirType::ClassF, AirType::ClassG, AirType::Other][..],
+            ),
+            (
+                "microlight",
+                self.microlight,
+                &[AirType::ClassF, AirType::ClassG, AirType::Other][..],
+            ),
+        ] {
+            if let Some(air_type) = value {
+                if !plausible.contains(&air_type) {
+                    warnings.push(Warning {
+                        name: name.to_string(),
+                        value: airtype_value(air_type).to_string(),
+                    });
                 }
             }
-            // Clear all LOAs
-            Action::ClearLoa => set.loa.clear(),
-            // Clear all RATs
-            Action::ClearRat => set.rat.clear(),
-            // Clear all Wave boxes
-            Action::ClearWave => set.wave.clear(),
         }
-        Self { settings: set }.into()
+        warnings
+    }
+
+    // Plain English summary, e.g. "CTR as ATZ, up to FL195, radio
+    // frequencies on, 3 LOAs, 2 RATs, OpenAir format."
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("{} as ATZ", airtype_label(self.atz))];
+
+        parts.push(if self.max_level >= MAX_LEVEL {
+            "no maximum level".to_string()
+        } else {
+            format!("up to FL{}", self.max_level)
+        });
+        if self.min_level > 0 {
+            parts.push(format!("from FL{}", self.min_level));
+        }
+        parts.push(format!(
+            "radio frequencies {}",
+            if self.radio { "on" } else { "off" }
+        ));
+
+        for (count, singular, plural_name) in [
+            (self.loa.len(), "LOA", "LOAs"),
+            (self.rat.len(), "RAT", "RATs"),
+            (self.wave.len(), "wave box", "wave boxes"),
+            (
+                self.exclude.len(),
+                "excluded airspace",
+                "excluded airspaces",
+            ),
+        ] {
+            if count > 0 {
+                parts.push(plural(count, singular, plural_name));
+            }
+        }
+        parts.push(format!("{} format", format_label(self.format)));
+
+        parts.join(", ") + "."
+    }
+
+    // Same settings, ignoring RAT selection order. Sets already compare
+    // independent of order
+    pub fn canonical_eq(&self, other: &Settings) -> bool {
+        let canonical = |settings: &Settings| {
+            let mut settings = settings.clone();
+            settings.rat.sort();
+            settings
+        };
+        canonical(self) == canonical(other)
+    }
+
+    // Hint for overlay selections that won't be useful with the other
+    // settings
+    pub fn overlay_conflicts(&self) -> Option<String> {
+        if !self.overlay.is_empty()
+            && matches!(
+                self.format,
+                Format::Tnp
+                    | Format::GeoJson
+                    | Format::Gpx
+                    | Format::Cup
+                    | Format::Kml
+                    | Format::Csv
+            )
+        {
+            return Some("Overlays are only included in OpenAir output".to_string());
+        }
+
+        let mut overlays = self.overlay.iter().collect::<Vec<&Overlay>>();
+        overlays.sort();
+        overlays.into_iter().find_map(|overlay| {
+            let level = match overlay {
+                Overlay::FL195 => 195,
+                Overlay::FL105 => 105,
+                Overlay::Custom(level) => *level,
+                Overlay::AtzDz => return None,
+            };
+            (self.max_level < level).then(|| {
+                format!(
+                    "FL{} overlay is above the maximum level (FL{})",
+                    level, self.max_level
+                )
+            })
+        })
     }
 }
 
-// Default mapping value to airspace type
-fn get_airtype(value: &str) -> Option<AirType> {
-    match value {
-        "classd" => Some(AirType::ClassD),
-        "classf" => Some(AirType::ClassF),
-        "classg" => Some(AirType::ClassG),
-        "ctr" => Some(AirType::Ctr),
-        "danger" => Some(AirType::Danger),
-        "restricted" => Some(AirType::Restricted),
-        "gsec" => Some(AirType::Gliding),
-        _ => None,
+// Implausible setting
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Warning {
+    pub name: String,
+    pub value: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unusual type \"{}\" for setting \"{}\"",
+            self.value, self.name
+        )
+    }
+}
+
+// Upgrade settings JSON from older versions. Payloads without a version
+// number are version 0
+fn upgrade(value: &mut Value) {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+
+    if version < 1 {
+        // Single (or null) overlay becomes a list
+        if let Some(overlay) = value.get_mut("overlay") {
+            match overlay {
+                Value::Null => *overlay = Value::Array(vec![]),
+                Value::Array(_) => (),
+                _ => *overlay = Value::Array(vec![overlay.take()]),
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object_mut() {
+        object.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+    }
+}
+
+// Settings from JSON of any version
+pub fn try_migrate(mut value: Value) -> Result<Settings, serde_json::Error> {
+    upgrade(&mut value);
+    serde_json::from_value(value)
+}
+
+// Settings from JSON of any version with renamed LOA/RAT/Wave selections
+// updated, default if unreadable
+pub fn migrate(value: Value, renames: &[(&str, &str)]) -> Settings {
+    let mut settings = try_migrate(value).unwrap_or_default();
+    rename_selection(&mut settings.loa, renames);
+    rename_selection(&mut settings.rat, renames);
+    dedupe(&mut settings.rat);
+    rename_selection(&mut settings.wave, renames);
+    settings
+}
+
+// Errors from strict settings parser
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SettingsError {
+    UnknownName(String),
+    InvalidValue { name: String, value: String },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::UnknownName(name) => write!(f, "unknown setting \"{}\"", name),
+            SettingsError::InvalidValue { name, value } => {
+                write!(f, "invalid value \"{}\" for setting \"{}\"", value, name)
+            }
+        }
+    }
+}
+
+// Unrecognised airspace type value
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownAirType(pub String);
+
+impl fmt::Display for UnknownAirType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown airspace type \"{}\"", self.0)
+    }
+}
+
+// Kind of selected name
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum SelectionKind {
+    Loa,
+    Rat,
+    Wave,
+}
+
+impl SelectionKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            SelectionKind::Loa => "LOA",
+            SelectionKind::Rat => "RAT",
+            SelectionKind::Wave => "Wave",
+        }
+    }
+}
+
+// Difference between two sets of settings
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingChange {
+    Changed {
+        name: String,
+        before: Option<String>,
+        after: Option<String>,
+    },
+    Added {
+        name: String,
+        value: String,
+    },
+    Removed {
+        name: String,
+        value: String,
+    },
+}
+
+// List differences going from settings a to settings b. Scalar options
+// are compared using their query string values, with None for unset
+// options, and LOA/RAT/Wave/Exclude entries as sorted additions/removals
+pub fn diff(a: &Settings, b: &Settings) -> Vec<SettingChange> {
+    let scalars = |set: &Settings| {
+        form_urlencoded::parse(set.to_full_query_string().as_bytes())
+            .into_owned()
+            .filter(|(name, _)| {
+                !matches!(
+                    name.as_str(),
+                    "loa"
+                        | "rat"
+                        | "wave"
+                        | "exclude"
+                        | "gliding_site"
+                        | "overlay"
+                        | "disable"
+                        | "cap"
+                )
+            })
+            .collect::<Vec<(String, String)>>()
+    };
+    let before = scalars(a);
+    let after = scalars(b);
+
+    let lookup = |pairs: &[(String, String)], name: &str| {
+        pairs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.clone())
+    };
+
+    let mut names = before
+        .iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<&String>>();
+    for (name, _) in after.iter() {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let mut changes = Vec::new();
+    for name in names {
+        let (old, new) = (lookup(&before, name), lookup(&after, name));
+        if old != new {
+            changes.push(SettingChange::Changed {
+                name: name.clone(),
+                before: old,
+                after: new,
+            });
+        }
+    }
+
+    // Repeated query string values
+    let repeated = |set: &Settings, key: &str| {
+        form_urlencoded::parse(set.to_full_query_string().as_bytes())
+            .into_owned()
+            .filter(|(name, _)| name == key)
+            .map(|(_, value)| value)
+            .collect::<HashSet<String>>()
+    };
+    let (old_overlay, new_overlay) = (repeated(a, "overlay"), repeated(b, "overlay"));
+    let (old_disable, new_disable) = (repeated(a, "disable"), repeated(b, "disable"));
+    let (old_cap, new_cap) = (repeated(a, "cap"), repeated(b, "cap"));
+    let rats = |set: &Settings| set.rat.iter().cloned().collect::<HashSet<String>>();
+    let (old_rat, new_rat) = (rats(a), rats(b));
+
+    for (name, old, new) in [
+        ("overlay", &old_overlay, &new_overlay),
+        ("disable", &old_disable, &new_disable),
+        ("cap", &old_cap, &new_cap),
+        ("loa", &a.loa, &b.loa),
+        ("rat", &old_rat, &new_rat),
+        ("wave", &a.wave, &b.wave),
+        ("exclude", &a.exclude, &b.exclude),
+        ("gliding_site", &a.gliding_sites, &b.gliding_sites),
+    ] {
+        let mut added = new.difference(old).collect::<Vec<&String>>();
+        added.sort();
+        changes.extend(added.into_iter().map(|value| SettingChange::Added {
+            name: name.to_string(),
+            value: value.clone(),
+        }));
+
+        let mut removed = old.difference(new).collect::<Vec<&String>>();
+        removed.sort();
+        changes.extend(removed.into_iter().map(|value| SettingChange::Removed {
+            name: name.to_string(),
+            value: value.clone(),
+        }));
+    }
+
+    changes
+}
+
+impl fmt::Display for SettingChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unset = |value: &Option<String>| value.clone().unwrap_or("unset".to_string());
+        match self {
+            SettingChange::Changed {
+                name,
+                before,
+                after,
+            } => write!(f, "{}: {} to {}", name, unset(before), unset(after)),
+            SettingChange::Added { name, value } => write!(f, "{}: added {}", name, value),
+            SettingChange::Removed { name, value } => write!(f, "{}: removed {}", name, value),
+        }
+    }
+}
+
+// Named settings
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Profile {
+    pub name: String,
+    pub settings: Settings,
+}
+
+// Settings overrides, e.g. personal changes to a club profile. Unset
+// fields leave the base value unchanged
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct PartialSettings {
+    pub atz: Option<AirType>,
+    pub ils: Option<Option<AirType>>,
+    pub matz: Option<Option<AirType>>,
+    pub unlicensed: Option<Option<AirType>>,
+    pub microlight: Option<Option<AirType>>,
+    pub gliding: Option<Option<AirType>>,
+    pub home: Option<Option<String>>,
+    pub home_radius_nm: Option<Option<u16>>,
+    pub home_coords: Option<Option<(f64, f64)>>,
+    pub atz_buffer_nm: Option<Option<f64>>,
+    pub competition_center: Option<Option<String>>,
+    pub competition_radius_nm: Option<Option<u16>>,
+    pub bbox: Option<Option<(f64, f64, f64, f64)>>,
+    pub hirta_gvs: Option<Option<AirType>>,
+    pub obstacle: Option<Option<AirType>>,
+    pub obstacle_min_ft: Option<Option<u16>>,
+    pub wave_min_fl: Option<Option<u16>>,
+    pub max_level: Option<u16>,
+    pub min_level: Option<u16>,
+    pub radio: Option<bool>,
+    pub radio_as_comment: Option<bool>,
+    pub colorize: Option<bool>,
+    pub low_only: Option<bool>,
+    pub altitude_ref: Option<AltitudeRef>,
+    pub wave_clip: Option<WaveClip>,
+    pub name_prefix: Option<Option<String>>,
+    pub author: Option<Option<String>>,
+    pub data_release: Option<Option<String>>,
+    pub active_now: Option<bool>,
+    pub crlf: Option<bool>,
+    pub dedupe_names: Option<bool>,
+    pub split_by_type: Option<bool>,
+    pub freq_decimals: Option<u8>,
+    pub include_notes: Option<bool>,
+    pub require_frequency: Option<bool>,
+    pub label_points: Option<bool>,
+    pub exclude_inactive_danger: Option<bool>,
+    pub arc_resolution: Option<u16>,
+    pub max_features: Option<Option<usize>>,
+    pub types: Option<HashMap<AirType, bool>>,
+    pub type_caps: Option<HashMap<AirType, u16>>,
+    pub format: Option<Format>,
+    pub rat_from: Option<Option<String>>,
+    pub overlay: Option<HashSet<Overlay>>,
+    pub loa: Option<HashSet<String>>,
+    pub rat: Option<Vec<String>>,
+    pub wave: Option<HashSet<String>>,
+    pub exclude: Option<HashSet<String>>,
+    pub gliding_sites: Option<HashSet<String>>,
+}
+
+// Combine base settings with overrides. Scalar options are replaced and
+// LOA/RAT/Wave/Exclude/overlay selections are added to the base
+pub fn merge(base: &Settings, overrides: &PartialSettings) -> Settings {
+    let mut settings = base.clone();
+    let overrides = overrides.clone();
+
+    if let Some(atz) = overrides.atz {
+        settings.atz = atz;
+    }
+    if let Some(ils) = overrides.ils {
+        settings.ils = ils;
+    }
+    if let Some(matz) = overrides.matz {
+        settings.matz = matz;
+    }
+    if let Some(unlicensed) = overrides.unlicensed {
+        settings.unlicensed = unlicensed;
+    }
+    if let Some(microlight) = overrides.microlight {
+        settings.microlight = microlight;
+    }
+    if let Some(gliding) = overrides.gliding {
+        settings.gliding = gliding;
+    }
+    if let Some(home) = overrides.home {
+        settings.home = home;
+    }
+    if let Some(home_radius_nm) = overrides.home_radius_nm {
+        settings.home_radius_nm = home_radius_nm;
+    }
+    if let Some(atz_buffer_nm) = overrides.atz_buffer_nm {
+        settings.atz_buffer_nm = atz_buffer_nm;
+    }
+    if let Some(competition_center) = overrides.competition_center {
+        settings.competition_center = competition_center;
+    }
+    if let Some(competition_radius_nm) = overrides.competition_radius_nm {
+        settings.competition_radius_nm = competition_radius_nm;
+    }
+    if let Some(home_coords) = overrides.home_coords {
+        settings.home_coords = home_coords;
+    }
+    if let Some(bbox) = overrides.bbox {
+        settings.bbox = bbox;
+    }
+    if let Some(hirta_gvs) = overrides.hirta_gvs {
+        settings.hirta_gvs = hirta_gvs;
+    }
+    if let Some(obstacle) = overrides.obstacle {
+        settings.obstacle = obstacle;
+    }
+    if let Some(obstacle_min_ft) = overrides.obstacle_min_ft {
+        settings.obstacle_min_ft = obstacle_min_ft;
+    }
+    if let Some(wave_min_fl) = overrides.wave_min_fl {
+        settings.wave_min_fl = wave_min_fl;
+    }
+    if let Some(max_level) = overrides.max_level {
+        settings.max_level = max_level;
+    }
+    if let Some(min_level) = overrides.min_level {
+        settings.min_level = min_level;
+    }
+    if let Some(radio) = overrides.radio {
+        settings.radio = radio;
+    }
+    if let Some(radio_as_comment) = overrides.radio_as_comment {
+        settings.radio_as_comment = radio_as_comment;
+    }
+    if let Some(colorize) = overrides.colorize {
+        settings.colorize = colorize;
+    }
+    if let Some(low_only) = overrides.low_only {
+        settings.low_only = low_only;
+    }
+    if let Some(altitude_ref) = overrides.altitude_ref {
+        settings.altitude_ref = altitude_ref;
+    }
+    if let Some(wave_clip) = overrides.wave_clip {
+        settings.wave_clip = wave_clip;
+    }
+    if let Some(name_prefix) = overrides.name_prefix {
+        settings.name_prefix = name_prefix;
+    }
+    if let Some(author) = overrides.author {
+        settings.author = author;
+    }
+    if let Some(data_release) = overrides.data_release {
+        settings.data_release = data_release;
+    }
+    if let Some(active_now) = overrides.active_now {
+        settings.active_now = active_now;
+    }
+    if let Some(crlf) = overrides.crlf {
+        settings.crlf = crlf;
+    }
+    if let Some(dedupe_names) = overrides.dedupe_names {
+        settings.dedupe_names = dedupe_names;
+    }
+    if let Some(split_by_type) = overrides.split_by_type {
+        settings.split_by_type = split_by_type;
+    }
+    if let Some(freq_decimals) = overrides.freq_decimals {
+        settings.freq_decimals = freq_decimals;
+    }
+    if let Some(arc_resolution) = overrides.arc_resolution {
+        settings.arc_resolution = arc_resolution;
+    }
+    if let Some(include_notes) = overrides.include_notes {
+        settings.include_notes = include_notes;
+    }
+    if let Some(require_frequency) = overrides.require_frequency {
+        settings.require_frequency = require_frequency;
+    }
+    if let Some(label_points) = overrides.label_points {
+        settings.label_points = label_points;
+    }
+    if let Some(exclude_inactive_danger) = overrides.exclude_inactive_danger {
+        settings.exclude_inactive_danger = exclude_inactive_danger;
+    }
+    if let Some(max_features) = overrides.max_features {
+        settings.max_features = max_features;
+    }
+    if let Some(types) = overrides.types {
+        settings.types.extend(types);
+    }
+    if let Some(type_caps) = overrides.type_caps {
+        settings.type_caps.extend(type_caps);
+    }
+    if let Some(format) = overrides.format {
+        settings.format = format;
+    }
+    if let Some(rat_from) = overrides.rat_from {
+        settings.rat_from = rat_from;
+    }
+    if let Some(overlay) = overrides.overlay {
+        settings.overlay.extend(overlay);
+    }
+    if let Some(loa) = overrides.loa {
+        settings.loa.extend(loa);
+    }
+    if let Some(rat) = overrides.rat {
+        settings.rat.extend(rat);
+        dedupe(&mut settings.rat);
+    }
+    if let Some(wave) = overrides.wave {
+        settings.wave.extend(wave);
+    }
+    if let Some(exclude) = overrides.exclude {
+        settings.exclude.extend(exclude);
+    }
+    if let Some(gliding_sites) = overrides.gliding_sites {
+        settings.gliding_sites.extend(gliding_sites);
+    }
+
+    settings
+}
+
+// As merge, but options the user has set (by Action::Set name) keep their
+// base value
+pub fn merge_untouched(
+    base: &Settings,
+    overrides: &PartialSettings,
+    touched: &HashSet<String>,
+) -> Settings {
+    let mut overrides = overrides.clone();
+    for name in touched {
+        match name.as_str() {
+            "atz" => overrides.atz = None,
+            "ils" => overrides.ils = None,
+            "matz" => overrides.matz = None,
+            "unlicensed" => overrides.unlicensed = None,
+            "microlight" => overrides.microlight = None,
+            "gliding" => overrides.gliding = None,
+            "home" => overrides.home = None,
+            "home_radius" => overrides.home_radius_nm = None,
+            "home_coords" => overrides.home_coords = None,
+            "atz_buffer" => overrides.atz_buffer_nm = None,
+            "competition_center" => overrides.competition_center = None,
+            "competition_radius" => overrides.competition_radius_nm = None,
+            "bbox" => overrides.bbox = None,
+            "hirta_gvs" => overrides.hirta_gvs = None,
+            "obstacle" => overrides.obstacle = None,
+            "obstacle_min_ft" => overrides.obstacle_min_ft = None,
+            "wave_min_fl" => overrides.wave_min_fl = None,
+            "max_level" => overrides.max_level = None,
+            "min_level" => overrides.min_level = None,
+            "radio" => overrides.radio = None,
+            "radio_as_comment" => overrides.radio_as_comment = None,
+            "colorize" => overrides.colorize = None,
+            "low_only" => overrides.low_only = None,
+            "altitude_ref" => overrides.altitude_ref = None,
+            "wave_clip" => overrides.wave_clip = None,
+            "name_prefix" => overrides.name_prefix = None,
+            "author" => overrides.author = None,
+            "data_release" => overrides.data_release = None,
+            "active_now" => overrides.active_now = None,
+            "crlf" => overrides.crlf = None,
+            "dedupe_names" => overrides.dedupe_names = None,
+            "split_by_type" => overrides.split_by_type = None,
+            "freq_decimals" => overrides.freq_decimals = None,
+            "include_notes" => overrides.include_notes = None,
+            "require_frequency" => overrides.require_frequency = None,
+            "label_points" => overrides.label_points = None,
+            "exclude_inactive_danger" => overrides.exclude_inactive_danger = None,
+            "arc_resolution" => overrides.arc_resolution = None,
+            "max_features" => overrides.max_features = None,
+            "disable" => overrides.types = None,
+            "cap" => overrides.type_caps = None,
+            "format" => overrides.format = None,
+            "rat_from" => overrides.rat_from = None,
+            "overlay" => overrides.overlay = None,
+            _ => (),
+        }
+    }
+
+    merge(base, &overrides)
+}
+
+// Highest selectable flight level
+const MAX_LEVEL: u16 = 660;
+
+// Largest ATZ radius buffer, nm
+const MAX_ATZ_BUFFER: f64 = 5.0;
+
+// Range of segments per full circle for tessellated output
+const MIN_ARC_RESOLUTION: u16 = 8;
+const MAX_ARC_RESOLUTION: u16 = 360;
+
+// Maximum number of undo steps
+const MAX_HISTORY: usize = 50;
+
+// Longest data release tag
+const MAX_RELEASE_LEN: usize = 40;
+
+// LOA/RAT/Wave names renamed between releases, old name first. Add an
+// entry whenever the source data renames a selectable item
+pub const SELECTION_RENAMES: &[(&str, &str)] = &[];
+
+// Application state
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct State {
+    pub settings: Settings,
+    pub saved_settings: Settings,
+    pub undo: VecDeque<Settings>,
+    pub redo: Vec<Settings>,
+    pub profiles: Vec<Profile>,
+    pub touched: HashSet<String>,
+    #[serde(skip)]
+    pub import_error: Option<String>,
+    // AIRAC date and commit of the loaded airspace data
+    #[serde(skip)]
+    pub loaded_release: Option<(String, String)>,
+    // Shared link differences from the stored settings
+    #[serde(skip)]
+    pub link_changes: Vec<SettingChange>,
+}
+
+impl State {
+    // Name of a saved profile with the current settings
+    pub fn active_profile(&self) -> Option<String> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.settings.canonical_eq(&self.settings))
+            .map(|profile| profile.name.clone())
+    }
+
+    // Settings have changed since last saved
+    pub fn is_dirty(&self) -> bool {
+        self.settings != self.saved_settings
+    }
+
+    // Pinned data release isn't the one loaded, so the latest data is being
+    // used instead. Releases are identified by AIRAC date or commit prefix
+    pub fn release_fallback(&self) -> bool {
+        match (&self.settings.data_release, &self.loaded_release) {
+            (Some(release), Some((airac_date, commit))) => {
+                !(airac_date.starts_with(release.as_str()) || commit.starts_with(release.as_str()))
+            }
+            _ => false,
+        }
+    }
+
+    // Whole working session, including undo history and profiles, as JSON
+    pub fn to_session_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    // Session from JSON, upgrading settings saved by older versions
+    pub fn from_session_json(json: &str) -> Result<State, serde_json::Error> {
+        let mut value: Value = serde_json::from_str(json)?;
+
+        for name in ["settings", "saved_settings"] {
+            if let Some(settings) = value.get_mut(name) {
+                upgrade(settings);
+            }
+        }
+        for name in ["undo", "redo"] {
+            if let Some(Value::Array(history)) = value.get_mut(name) {
+                history.iter_mut().for_each(upgrade);
+            }
+        }
+        if let Some(Value::Array(profiles)) = value.get_mut("profiles") {
+            for profile in profiles.iter_mut() {
+                if let Some(settings) = profile.get_mut("settings") {
+                    upgrade(settings);
+                }
+            }
+        }
+
+        serde_json::from_value(value)
+    }
+}
+
+// State actions
+#[derive(Debug)]
+pub enum Action {
+    Set {
+        name: String,
+        value: String,
+    },
+    SetMany {
+        entries: Vec<(String, String)>,
+    },
+    SetLoa {
+        name: String,
+        checked: bool,
+    },
+    SetLoaCategory {
+        category: String,
+        checked: bool,
+        members: Vec<String>,
+    },
+    SetRat {
+        name: String,
+        checked: bool,
+    },
+    MoveRat {
+        name: String,
+        to_index: usize,
+    },
+    SetWave {
+        name: String,
+        checked: bool,
+    },
+    SetWaveRange {
+        prefix: String,
+        checked: bool,
+        all: Vec<String>,
+    },
+    SetExclude {
+        name: String,
+        checked: bool,
+    },
+    SetGlidingSite {
+        name: String,
+        checked: bool,
+    },
+    ClearGlidingSites,
+    SetOverlay {
+        name: String,
+        checked: bool,
+    },
+    ClearOverlay,
+    SetFormat(Format),
+    SetTypeEnabled {
+        air_type: AirType,
+        enabled: bool,
+    },
+    SetTypeCap {
+        air_type: AirType,
+        level: Option<u16>,
+    },
+    SetMaxLevelPreset(MaxLevelPreset),
+    SetRadio(bool),
+    ToggleRadio,
+    SetHomeCoords {
+        lat: f64,
+        lon: f64,
+    },
+    ClearHomeCoords,
+    ClearAll,
+    ClearLoa,
+    ClearRat,
+    ClearWave,
+    ClearExclude,
+    InvertLoa {
+        all: Vec<String>,
+    },
+    InvertRat {
+        all: Vec<String>,
+    },
+    InvertWave {
+        all: Vec<String>,
+    },
+    InvertExclude {
+        all: Vec<String>,
+    },
+    SelectAllLoa {
+        names: Vec<String>,
+    },
+    SelectAllRat {
+        names: Vec<String>,
+    },
+    SelectAllWave {
+        names: Vec<String>,
+    },
+    SelectAllExclude {
+        names: Vec<String>,
+    },
+    TouchSetting {
+        name: String,
+    },
+    ReplaceLoa {
+        names: HashSet<String>,
+    },
+    ReplaceRat {
+        names: Vec<String>,
+    },
+    ReplaceWave {
+        names: HashSet<String>,
+    },
+    Reset,
+    RenameActiveSelection {
+        loa: Vec<String>,
+        rat: Vec<String>,
+        wave: Vec<String>,
+    },
+    SaveProfile {
+        name: String,
+    },
+    LoadProfile {
+        name: String,
+    },
+    ImportSettings {
+        text: String,
+    },
+    MergeSettings {
+        text: String,
+    },
+    RestoreSession {
+        text: String,
+    },
+    MarkSaved,
+    SetLoadedRelease {
+        airac_date: String,
+        commit: String,
+    },
+    Undo,
+    Redo,
+}
+
+impl Reducible for State {
+    type Action = Action;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        // Action description for debug trace
+        #[cfg(debug_assertions)]
+        let trace = log::log_enabled!(log::Level::Debug).then(|| format!("{:?}", action));
+
+        let state = self.clone().apply(action);
+
+        // Trace action and resulting setting changes
+        #[cfg(debug_assertions)]
+        if let Some(trace) = trace {
+            log::debug!("{} -> {:?}", trace, diff(&self.settings, &state.settings));
+        }
+
+        state
+    }
+}
+
+impl State {
+    // New state after action
+    fn apply(self: Rc<Self>, action: Action) -> Rc<Self> {
+        let mut state = (*self).clone();
+        let set = &mut state.settings;
+        match action {
+            // Set airspace option
+            Action::Set { name, value } => {
+                apply_set(set, &name, &value);
+                state.touched.insert(name);
+            }
+            // Set several airspace options
+            Action::SetMany { entries } => {
+                for (name, value) in entries {
+                    apply_set(set, &name, &value);
+                    state.touched.insert(name);
+                }
+            }
+            // Include/exclude airspace type
+            Action::SetTypeEnabled { air_type, enabled } => {
+                set_type_enabled(set, air_type, enabled);
+                state.touched.insert("disable".to_string());
+            }
+            // Per airspace type level cap, applied as well as maximum level
+            Action::SetTypeCap { air_type, level } => {
+                match level {
+                    Some(level) => {
+                        set.type_caps.insert(air_type, clamp_max_level(level));
+                    }
+                    None => {
+                        set.type_caps.remove(&air_type);
+                    }
+                }
+                state.touched.insert("cap".to_string());
+            }
+            // Include/exclude LOA
+            Action::SetLoa { name, checked } => {
+                if checked {
+                    set.loa.replace(name);
+                } else {
+                    set.loa.remove(&name);
+                }
+            }
+            // Include/exclude all LOAs in a category, whatever their
+            // current selection
+            Action::SetLoaCategory {
+                category,
+                checked,
+                members,
+            } => {
+                log::debug!("LOA category {}: {} members", category, members.len());
+                for name in members {
+                    if checked {
+                        set.loa.replace(name);
+                    } else {
+                        set.loa.remove(&name);
+                    }
+                }
+            }
+            // Include/exclude RAT
+            Action::SetRat { name, checked } => {
+                if !checked {
+                    set.rat.retain(|rat| *rat != name);
+                } else if !set.rat.contains(&name) {
+                    set.rat.push(name);
+                }
+            }
+            // Change RAT output order, indices past the end move to last
+            Action::MoveRat { name, to_index } => {
+                if let Some(index) = set.rat.iter().position(|rat| *rat == name) {
+                    let rat = set.rat.remove(index);
+                    set.rat.insert(to_index.min(set.rat.len()), rat);
+                }
+            }
+            // Include/exclude wave box
+            Action::SetWave { name, checked } => {
+                if checked {
+                    set.wave.replace(name);
+                } else {
+                    set.wave.remove(&name);
+                }
+            }
+            // Include/exclude all wave boxes with name prefix
+            Action::SetWaveRange {
+                prefix,
+                checked,
+                all,
+            } => {
+                for name in all.into_iter().filter(|name| name.starts_with(&prefix)) {
+                    if checked {
+                        set.wave.replace(name);
+                    } else {
+                        set.wave.remove(&name);
+                    }
+                }
+            }
+            // Include/exclude named airspace
+            Action::SetExclude { name, checked } => {
+                if checked {
+                    set.exclude.replace(name);
+                } else {
+                    set.exclude.remove(&name);
+                }
+            }
+            // Include/exclude individual gliding site, all sites are
+            // included if none are selected
+            Action::SetGlidingSite { name, checked } => {
+                if checked {
+                    set.gliding_sites.replace(name);
+                } else {
+                    set.gliding_sites.remove(&name);
+                }
+            }
+            Action::ClearGlidingSites => set.gliding_sites.clear(),
+            // Set/clear home position
+            Action::SetHomeCoords { lat, lon } => {
+                if valid_coords(lat, lon) {
+                    set.home_coords = Some((lat, lon));
+                    state.touched.insert("home_coords".to_string());
+                }
+            }
+            Action::ClearHomeCoords => {
+                set.home_coords = None;
+                state.touched.insert("home_coords".to_string());
+            }
+            // Include/exclude altitude overlay
+            Action::SetOverlay { name, checked } => {
+                if let Some(overlay) = parse_overlay(&name) {
+                    if checked {
+                        set.overlay.insert(overlay);
+                    } else {
+                        set.overlay.remove(&overlay);
+                    }
+                    state.touched.insert("overlay".to_string());
+                }
+            }
+            // Clear all overlays
+            Action::ClearOverlay => {
+                set.overlay.clear();
+                state.touched.insert("overlay".to_string());
+            }
+            // Set output format
+            Action::SetFormat(format) => {
+                set.format = format;
+                state.touched.insert("format".to_string());
+            }
+            // Set maximum level from preset
+            Action::SetMaxLevelPreset(preset) => {
+                set.max_level = preset.level();
+                state.touched.insert("max_level".to_string());
+            }
+            // Enable/disable radio frequencies
+            Action::SetRadio(radio) => {
+                set.radio = radio;
+                state.touched.insert("radio".to_string());
+            }
+            Action::ToggleRadio => {
+                set.radio = !set.radio;
+                state.touched.insert("radio".to_string());
+            }
+            // Clear all LOAs, RATs and Wave boxes
+            Action::ClearAll => {
+                set.loa.clear();
+                set.rat.clear();
+                set.wave.clear();
+            }
+            // Clear all LOAs
+            Action::ClearLoa => set.loa.clear(),
+            // Clear all RATs
+            Action::ClearRat => set.rat.clear(),
+            // Clear all Wave boxes
+            Action::ClearWave => set.wave.clear(),
+            // Clear all excluded airspace
+            Action::ClearExclude => set.exclude.clear(),
+            // Invert LOA selection
+            Action::InvertLoa { all } => set.loa = invert(&set.loa, all),
+            // Invert RAT selection
+            Action::InvertRat { all } => {
+                set.rat = all.into_iter().filter(|x| !set.rat.contains(x)).collect();
+                dedupe(&mut set.rat);
+            }
+            // Invert Wave box selection
+            Action::InvertWave { all } => set.wave = invert(&set.wave, all),
+            // Invert excluded airspace selection
+            Action::InvertExclude { all } => set.exclude = invert(&set.exclude, all),
+            // Select all LOAs
+            Action::SelectAllLoa { names } => set.loa.extend(names),
+            // Select all RATs
+            Action::SelectAllRat { names } => {
+                set.rat.extend(names);
+                dedupe(&mut set.rat);
+            }
+            // Select all Wave boxes
+            Action::SelectAllWave { names } => set.wave.extend(names),
+            // Exclude all airspace
+            Action::SelectAllExclude { names } => set.exclude.extend(names),
+            // Mark option as deliberately set by the user, e.g. to keep
+            // its default value when merging
+            Action::TouchSetting { name } => {
+                state.touched.insert(name);
+            }
+            // Replace whole LOA, RAT or Wave box selection, e.g. from a
+            // shared link
+            Action::ReplaceLoa { names } => set.loa = names,
+            Action::ReplaceRat { names } => {
+                set.rat = names;
+                dedupe(&mut set.rat);
+            }
+            Action::ReplaceWave { names } => set.wave = names,
+            // Restore default settings
+            Action::Reset => {
+                *set = Settings::default();
+                state.touched.clear();
+            }
+            // Update persisted selections to match current airspace data
+            Action::RenameActiveSelection { loa, rat, wave } => {
+                migrate_selection(&mut set.loa, SELECTION_RENAMES, &loa);
+                migrate_selection(&mut set.rat, SELECTION_RENAMES, &rat);
+                dedupe(&mut set.rat);
+                migrate_selection(&mut set.wave, SELECTION_RENAMES, &wave);
+            }
+            // Save current settings as named profile
+            Action::SaveProfile { name } => {
+                state.profiles.retain(|p| p.name != name);
+                state.profiles.push(Profile {
+                    name,
+                    settings: self.settings.clone(),
+                });
+                state.profiles.sort_by(|a, b| a.name.cmp(&b.name));
+                return state.into();
+            }
+            // Restore settings from named profile
+            Action::LoadProfile { name } => {
+                if let Some(profile) = self.profiles.iter().find(|p| p.name == name) {
+                    *set = profile.settings.clone();
+                }
+            }
+            // Replace settings with imported JSON or settings code,
+            // unchanged if invalid
+            Action::ImportSettings { text } => {
+                let imported = if text.trim_start().starts_with('{') {
+                    serde_json::from_str(&text)
+                        .and_then(try_migrate)
+                        .map_err(|err| err.to_string())
+                } else {
+                    Settings::from_base64(&text).map_err(|err| err.to_string())
+                };
+
+                match imported {
+                    Ok(settings) => {
+                        state.settings = settings;
+                        state.import_error = None;
+                    }
+                    Err(err) => {
+                        state.import_error = Some(err);
+                        return state.into();
+                    }
+                }
+            }
+            // Add settings overrides (JSON), e.g. club defaults, keeping
+            // options the user has set
+            Action::MergeSettings { text } => {
+                match serde_json::from_str::<PartialSettings>(&text) {
+                    Ok(overrides) => {
+                        *set = merge_untouched(set, &overrides, &state.touched);
+                        state.import_error = None;
+                    }
+                    Err(err) => {
+                        state.import_error = Some(err.to_string());
+                        return state.into();
+                    }
+                }
+            }
+            // Restore working session, keeping track of what is persisted
+            Action::RestoreSession { text } => {
+                match State::from_session_json(&text) {
+                    Ok(session) => {
+                        state = State {
+                            saved_settings: state.saved_settings,
+                            loaded_release: state.loaded_release,
+                            ..session
+                        };
+                    }
+                    Err(err) => state.import_error = Some(err.to_string()),
+                }
+                return state.into();
+            }
+            // Record settings as persisted
+            Action::MarkSaved => {
+                state.saved_settings = self.settings.clone();
+                return state.into();
+            }
+            // Record release of loaded airspace data
+            Action::SetLoadedRelease { airac_date, commit } => {
+                state.loaded_release = Some((airac_date, commit));
+                return state.into();
+            }
+            // Restore previous settings
+            Action::Undo => {
+                if let Some(prev) = state.undo.pop_back() {
+                    state
+                        .redo
+                        .push(std::mem::replace(&mut state.settings, prev));
+                }
+                return state.into();
+            }
+            // Restore undone settings
+            Action::Redo => {
+                if let Some(next) = state.redo.pop() {
+                    state
+                        .undo
+                        .push_back(std::mem::replace(&mut state.settings, next));
+                }
+                return state.into();
+            }
+        }
+
+        // Record edit in undo history
+        if state.settings != self.settings {
+            state.undo.push_back(self.settings.clone());
+            if state.undo.len() > MAX_HISTORY {
+                state.undo.pop_front();
+            }
+            state.redo.clear();
+        }
+
+        state.into()
+    }
+}
+
+// Set airspace option from name/value pair
+fn apply_set(set: &mut Settings, name: &str, value: &str) {
+    match name {
+        "atz" => {
+            set.atz = get_airtype(value).unwrap_or(AirType::Ctr);
+            set_type_enabled(set, set.atz, true);
+        }
+        "ils" if value == "atz" => set.ils = None,
+        "ils" | "matz" | "unlicensed" | "microlight" | "gliding" | "hirta_gvs" | "obstacle" => {
+            if let Some(air_type) = parse_optional_airtype(value) {
+                let field = match name {
+                    "ils" => &mut set.ils,
+                    "matz" => &mut set.matz,
+                    "unlicensed" => &mut set.unlicensed,
+                    "microlight" => &mut set.microlight,
+                    "gliding" => &mut set.gliding,
+                    "hirta_gvs" => &mut set.hirta_gvs,
+                    _ => &mut set.obstacle,
+                };
+                *field = air_type;
+                if let Some(air_type) = air_type {
+                    set_type_enabled(set, air_type, true);
+                }
+            }
+        }
+        "max_level" => {
+            set.max_level = parse_level(value)
+                .map(clamp_max_level)
+                .unwrap_or(set.max_level)
+        }
+        "min_level" => {
+            set.min_level = parse_level(value)
+                .map(clamp_max_level)
+                .unwrap_or(set.min_level)
+        }
+        "altitude_ref" => {
+            if let Some(altitude_ref) = parse_altitude_ref(value) {
+                set.altitude_ref = altitude_ref
+            }
+        }
+        "wave_clip" => {
+            if let Some(wave_clip) = parse_wave_clip(value) {
+                set.wave_clip = wave_clip
+            }
+        }
+        "disable" => {
+            if let Some(air_type) = get_airtype(value) {
+                set_type_enabled(set, air_type, false);
+            }
+        }
+        "cap" => {
+            if let Some((air_type, level)) = parse_type_cap(value) {
+                set.type_caps.insert(air_type, level);
+            }
+        }
+        "atz_buffer" => {
+            if value == "no" {
+                set.atz_buffer_nm = None
+            } else if let Some(buffer) = parse_atz_buffer(value) {
+                set.atz_buffer_nm = Some(buffer)
+            }
+        }
+        "competition_center" => {
+            set.competition_center = if value == "no" || value.is_empty() {
+                None
+            } else {
+                Some(value.to_string())
+            }
+        }
+        "competition_radius" => {
+            if value == "no" {
+                set.competition_radius_nm = None
+            } else if let Ok(radius) = value.parse::<u16>() {
+                set.competition_radius_nm = Some(radius)
+            }
+        }
+        "obstacle_min_ft" => {
+            if value == "no" {
+                set.obstacle_min_ft = None
+            } else if let Ok(height) = value.parse::<u16>() {
+                set.obstacle_min_ft = Some(height)
+            }
+        }
+        "wave_min_fl" => {
+            if value == "no" {
+                set.wave_min_fl = None
+            } else if let Some(level) = parse_level(value) {
+                set.wave_min_fl = Some(clamp_max_level(level))
+            }
+        }
+        "max_features" => {
+            if value == "no" {
+                set.max_features = None
+            } else if let Ok(max_features) = value.parse::<usize>() {
+                set.max_features = Some(max_features)
+            }
+        }
+        "crlf" => {
+            if let Some(crlf) = parse_bool(value) {
+                set.crlf = crlf
+            }
+        }
+        "dedupe_names" => {
+            if let Some(dedupe_names) = parse_bool(value) {
+                set.dedupe_names = dedupe_names
+            }
+        }
+        "split_by_type" => {
+            if let Some(split_by_type) = parse_bool(value) {
+                set.split_by_type = split_by_type
+            }
+        }
+        "include_notes" => {
+            if let Some(include_notes) = parse_bool(value) {
+                set.include_notes = include_notes
+            }
+        }
+        "require_frequency" => {
+            if let Some(require_frequency) = parse_bool(value) {
+                set.require_frequency = require_frequency
+            }
+        }
+        "label_points" => {
+            if let Some(label_points) = parse_bool(value) {
+                set.label_points = label_points
+            }
+        }
+        "exclude_inactive_danger" => {
+            if let Some(exclude_inactive_danger) = parse_bool(value) {
+                set.exclude_inactive_danger = exclude_inactive_danger
+            }
+        }
+        "freq_decimals" => {
+            if let Ok(decimals @ 2..=3) = value.parse::<u8>() {
+                set.freq_decimals = decimals
+            }
+        }
+        "arc_resolution" => {
+            if let Ok(resolution) = value.parse::<u16>() {
+                set.arc_resolution = resolution.clamp(MIN_ARC_RESOLUTION, MAX_ARC_RESOLUTION)
+            }
+        }
+        "active_now" => {
+            if let Some(active_now) = parse_bool(value) {
+                set.active_now = active_now
+            }
+        }
+        "low_only" => {
+            if let Some(low_only) = parse_bool(value) {
+                set.low_only = low_only
+            }
+        }
+        "colorize" => {
+            if let Some(colorize) = parse_bool(value) {
+                set.colorize = colorize
+            }
+        }
+        "radio_as_comment" => {
+            if let Some(radio_as_comment) = parse_bool(value) {
+                set.radio_as_comment = radio_as_comment
+            }
+        }
+        "radio" => {
+            if let Some(radio) = parse_bool(value) {
+                set.radio = radio
+            }
+        }
+        "rat_from" => {
+            if value.is_empty() || value == "no" {
+                set.rat_from = None
+            } else if parse_date(value).is_some() {
+                set.rat_from = Some(value.to_string())
+            }
+        }
+        "bbox" => {
+            if value.is_empty() || value == "no" {
+                set.bbox = None
+            } else if let Some(bbox) = parse_bbox(value) {
+                set.bbox = Some(bbox)
+            }
+        }
+        "home_coords" => {
+            if value.is_empty() || value == "no" {
+                set.home_coords = None
+            } else if let Some((lat, lon)) = parse_coords(value) {
+                set.home_coords = Some((lat, lon))
+            }
+        }
+        "name_prefix" => {
+            let prefix = value.replace(['\r', '\n'], " ").trim().to_string();
+            set.name_prefix = if prefix.is_empty() {
+                None
+            } else {
+                Some(prefix)
+            }
+        }
+        "author" => {
+            let author = value.replace(['\r', '\n'], " ").trim().to_string();
+            set.author = if author.is_empty() {
+                None
+            } else {
+                Some(author)
+            }
+        }
+        "data_release" => {
+            let release = value.trim();
+            if release.is_empty() || release == "latest" {
+                set.data_release = None
+            } else if valid_release(release) {
+                set.data_release = Some(release.to_string())
+            }
+        }
+        "home" => {
+            set.home = if value == "no" {
+                None
+            } else {
+                Some(value.to_string())
+            }
+        }
+        "home_radius" => {
+            if value == "no" {
+                set.home_radius_nm = None
+            } else if let Ok(radius) = value.parse::<u16>() {
+                set.home_radius_nm = Some(radius)
+            }
+        }
+        "overlay" => {
+            if value == "no" {
+                set.overlay.clear()
+            } else if let Some(overlay) = parse_overlay(value) {
+                set.overlay.insert(overlay);
+            }
+        }
+        "format" => set.format = parse_format(value).unwrap_or(Format::OpenAir),
+        _ => (),
+    }
+}
+
+// Include/exclude airspace type. Only excluded types are kept in the map.
+// Excluding a type turns off the optional features mapped to it, and
+// mapping a feature to a type (see apply_set) includes the type again
+fn set_type_enabled(set: &mut Settings, air_type: AirType, enabled: bool) {
+    if enabled {
+        set.types.remove(&air_type);
+        return;
+    }
+
+    set.types.insert(air_type, false);
+    for field in [
+        &mut set.matz,
+        &mut set.unlicensed,
+        &mut set.microlight,
+        &mut set.gliding,
+        &mut set.hirta_gvs,
+        &mut set.obstacle,
+    ] {
+        if *field == Some(air_type) {
+            *field = None;
+        }
+    }
+}
+
+// Overlay query string value
+fn overlay_name(overlay: &Overlay) -> String {
+    match overlay {
+        Overlay::FL195 => "fl195".to_string(),
+        Overlay::FL105 => "fl105".to_string(),
+        Overlay::AtzDz => "atzdz".to_string(),
+        Overlay::Custom(level) => format!("custom:{}", level),
+    }
+}
+
+// Overlay from value
+fn parse_overlay(value: &str) -> Option<Overlay> {
+    match value {
+        "fl195" => Some(Overlay::FL195),
+        "fl105" => Some(Overlay::FL105),
+        "atzdz" => Some(Overlay::AtzDz),
+        _ => parse_custom_overlay(value),
+    }
+}
+
+// Output format from value
+pub fn parse_format(value: &str) -> Option<Format> {
+    match value {
+        "openair" => Some(Format::OpenAir),
+        "ratonly" => Some(Format::RatOnly),
+        "competition" => Some(Format::Competition),
+        "tnp" => Some(Format::Tnp),
+        "geojson" => Some(Format::GeoJson),
+        "gpx" => Some(Format::Gpx),
+        "cup" => Some(Format::Cup),
+        "kml" => Some(Format::Kml),
+        "csv" => Some(Format::Csv),
+        "openair_lk" => Some(Format::OpenAirLk),
+        _ => None,
+    }
+}
+
+// Mapping output format to value, inverse of parse_format
+pub fn format_value(format: Format) -> &'static str {
+    match format {
+        Format::OpenAir => "openair",
+        Format::RatOnly => "ratonly",
+        Format::Competition => "competition",
+        Format::Tnp => "tnp",
+        Format::GeoJson => "geojson",
+        Format::Gpx => "gpx",
+        Format::Cup => "cup",
+        Format::Kml => "kml",
+        Format::Csv => "csv",
+        Format::OpenAirLk => "openair_lk",
+    }
+}
+
+// Output format display name
+fn format_label(format: Format) -> &'static str {
+    match format {
+        Format::OpenAir => "OpenAir",
+        Format::RatOnly => "RA(T) only",
+        Format::Competition => "Competition",
+        Format::Tnp => "TNP",
+        Format::GeoJson => "GeoJSON",
+        Format::Gpx => "GPX",
+        Format::Cup => "CUP",
+        Format::Kml => "KML",
+        Format::Csv => "CSV",
+        Format::OpenAirLk => "OpenAir (LK8000)",
+    }
+}
+
+// Altitude reference from value
+fn parse_altitude_ref(value: &str) -> Option<AltitudeRef> {
+    match value {
+        "asis" => Some(AltitudeRef::AsIs),
+        "agl" => Some(AltitudeRef::PreferAgl),
+        "amsl" => Some(AltitudeRef::PreferAmsl),
+        _ => None,
+    }
+}
+
+// Wave box clipping from value
+fn parse_wave_clip(value: &str) -> Option<WaveClip> {
+    match value {
+        "drop" => Some(WaveClip::Drop),
+        "clip" => Some(WaveClip::Clip),
+        "keep" => Some(WaveClip::Keep),
+        _ => None,
+    }
+}
+
+// Replace renamed selections
+fn rename_selection<C>(set: &mut C, renames: &[(&str, &str)])
+where
+    C: Default + IntoIterator<Item = String> + FromIterator<String>,
+{
+    *set = std::mem::take(set)
+        .into_iter()
+        .map(|name| {
+            renames
+                .iter()
+                .find(|(old, _)| *old == name)
+                .map(|(_, new)| new.to_string())
+                .unwrap_or(name)
+        })
+        .collect();
+}
+
+// Replace renamed selections and drop any no longer in the catalog
+fn migrate_selection<C>(set: &mut C, renames: &[(&str, &str)], catalog: &[String])
+where
+    C: Default + IntoIterator<Item = String> + FromIterator<String>,
+{
+    rename_selection(set, renames);
+    *set = std::mem::take(set)
+        .into_iter()
+        .filter(|name| catalog.contains(name))
+        .collect();
+}
+
+// Latitude/longitude in range
+fn valid_coords(lat: f64, lon: f64) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)
+}
+
+// Latitude/longitude from "lat,lon" decimal degrees
+pub fn parse_coords(value: &str) -> Option<(f64, f64)> {
+    let (lat, lon) = value.split_once(',')?;
+    let lat = lat.trim().parse::<f64>().ok()?;
+    let lon = lon.trim().parse::<f64>().ok()?;
+
+    valid_coords(lat, lon).then_some((lat, lon))
+}
+
+// Bounding box from "min_lat,min_lon,max_lat,max_lon", ignoring inverted
+// or out of range boxes
+fn parse_bbox(value: &str) -> Option<(f64, f64, f64, f64)> {
+    let values = value
+        .split(',')
+        .map(|x| x.trim().parse::<f64>().ok())
+        .collect::<Option<Vec<f64>>>()?;
+
+    match values[..] {
+        [min_lat, min_lon, max_lat, max_lon]
+            if valid_coords(min_lat, min_lon)
+                && valid_coords(max_lat, max_lon)
+                && min_lat < max_lat
+                && min_lon < max_lon =>
+        {
+            Some((min_lat, min_lon, max_lat, max_lon))
+        }
+        _ => None,
+    }
+}
+
+// Date from ISO (YYYY-MM-DD) value
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()
+}
+
+// Data release tag, e.g. AIRAC date or commit prefix, safe to use in a
+// file name
+pub fn valid_release(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_RELEASE_LEN
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+// Boolean from yes/no value
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "yes" | "true" => Some(true),
+        "no" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+// Names in all but not in selected
+fn invert(selected: &HashSet<String>, all: Vec<String>) -> HashSet<String> {
+    let all = all.into_iter().collect::<HashSet<String>>();
+    all.difference(selected).cloned().collect()
+}
+
+// Remove repeated names, keeping the first
+fn dedupe(names: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+}
+
+// ATZ radius buffer (nm), up to MAX_ATZ_BUFFER
+fn parse_atz_buffer(value: &str) -> Option<f64> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|buffer| *buffer > 0.0 && *buffer <= MAX_ATZ_BUFFER)
+}
+
+// Custom overlay level, e.g. "custom:120" or "custom:FL120"
+fn parse_custom_overlay(value: &str) -> Option<Overlay> {
+    let level = parse_level(value.strip_prefix("custom:")?)?;
+
+    (level > 0 && level <= MAX_LEVEL).then_some(Overlay::Custom(level))
+}
+
+// Flight level from number, with optional "FL" prefix
+fn parse_level(value: &str) -> Option<u16> {
+    let value = value.trim();
+    let level = match value.get(..2) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("fl") => &value[2..],
+        _ => value,
+    };
+
+    level.parse::<u16>().ok()
+}
+
+// Limit level to FL660, rounded to the nearest 5
+fn clamp_max_level(level: u16) -> u16 {
+    (level.min(MAX_LEVEL) + 2) / 5 * 5
+}
+
+// Airspace type and level cap from type:level, e.g. gsec:195
+fn parse_type_cap(value: &str) -> Option<(AirType, u16)> {
+    let (air_type, level) = value.split_once(':')?;
+    Some((get_airtype(air_type)?, clamp_max_level(parse_level(level)?)))
+}
+
+// Default mapping value to airspace type. The canonical values are
+// classa..classg for ICAO classes, plus awy (airway), cta, ctr, danger,
+// gsec (gliding sector), matz, other, prohibited, restricted, rmz and tmz
+fn get_airtype(value: &str) -> Option<AirType> {
+    get_airtype_strict(value).ok()
+}
+
+// Optional airspace type from value, Some(None) for an explicit "off",
+// "none" or "exclude" and None if the value isn't recognised
+fn parse_optional_airtype(value: &str) -> Option<Option<AirType>> {
+    match value.trim().to_lowercase().as_str() {
+        "off" | "none" | "exclude" => Some(None),
+        _ => get_airtype(value).map(Some),
+    }
+}
+
+// Mapping value to airspace type, with error for unknown values
+fn get_airtype_strict(value: &str) -> Result<AirType, UnknownAirType> {
+    match value.trim().to_lowercase().as_str() {
+        "awy" => Ok(AirType::Awy),
+        "classa" => Ok(AirType::ClassA),
+        "classb" => Ok(AirType::ClassB),
+        "classc" => Ok(AirType::ClassC),
+        "classd" => Ok(AirType::ClassD),
+        "classe" => Ok(AirType::ClassE),
+        "classf" => Ok(AirType::ClassF),
+        "classg" => Ok(AirType::ClassG),
+        "cta" => Ok(AirType::Cta),
+        "ctr" => Ok(AirType::Ctr),
+        "danger" => Ok(AirType::Danger),
+        "gsec" => Ok(AirType::Gliding),
+        "matz" => Ok(AirType::Matz),
+        "other" => Ok(AirType::Other),
+        "prohibited" => Ok(AirType::Prohibited),
+        "restricted" => Ok(AirType::Restricted),
+        "rmz" => Ok(AirType::Rmz),
+        "tmz" => Ok(AirType::Tmz),
+        _ => Err(UnknownAirType(value.to_string())),
+    }
+}
+
+// Airspace type display name
+pub fn airtype_label(air_type: AirType) -> &'static str {
+    match air_type {
+        AirType::Awy => "Airway",
+        AirType::ClassA => "Class A",
+        AirType::ClassB => "Class B",
+        AirType::ClassC => "Class C",
+        AirType::ClassD => "Class D",
+        AirType::ClassE => "Class E",
+        AirType::ClassF => "Class F",
+        AirType::ClassG => "Class G",
+        AirType::Danger => "Danger",
+        AirType::Cta => "CTA",
+        AirType::Ctr => "CTR",
+        AirType::Gliding => "Gliding",
+        AirType::Matz => "MATZ",
+        AirType::Other => "Other",
+        AirType::Prohibited => "Prohibited",
+        AirType::Restricted => "Restricted",
+        AirType::Rmz => "RMZ",
+        AirType::Tmz => "TMZ",
+    }
+}
+
+// Count with singular or plural noun, e.g. "1 LOA" or "3 LOAs"
+fn plural(count: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+// Mapping airspace type to value, inverse of get_airtype
+pub fn airtype_value(air_type: AirType) -> &'static str {
+    match air_type {
+        AirType::Awy => "awy",
+        AirType::ClassA => "classa",
+        AirType::ClassB => "classb",
+        AirType::ClassC => "classc",
+        AirType::ClassD => "classd",
+        AirType::ClassE => "classe",
+        AirType::ClassF => "classf",
+        AirType::ClassG => "classg",
+        AirType::Danger => "danger",
+        AirType::Cta => "cta",
+        AirType::Ctr => "ctr",
+        AirType::Gliding => "gsec",
+        AirType::Matz => "matz",
+        AirType::Other => "other",
+        AirType::Prohibited => "prohibited",
+        AirType::Restricted => "restricted",
+        AirType::Rmz => "rmz",
+        AirType::Tmz => "tmz",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Apply action to state
+    fn reduce(state: State, action: Action) -> State {
+        (*Rc::new(state).reduce(action)).clone()
+    }
+
+    fn set(name: &str, value: &str) -> Action {
+        Action::Set {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn max_level_garbage() {
+        let state = reduce(State::default(), set("max_level", "195"));
+        for value in ["garbage", ""] {
+            let next = reduce(state.clone(), set("max_level", value));
+            assert_eq!(next.settings, state.settings);
+        }
+    }
+
+    #[test]
+    fn max_level_clamped() {
+        for (value, level) in [("700", 660), ("3", 5), ("662", 660)] {
+            let state = reduce(State::default(), set("max_level", value));
+            assert_eq!(state.settings.max_level, level);
+        }
+    }
+
+    #[test]
+    fn max_level_prefix() {
+        for (value, level) in [("FL195", 195), ("fl105", 105), ("195", 195)] {
+            let state = reduce(State::default(), set("max_level", value));
+            assert_eq!(state.settings.max_level, level);
+        }
+
+        let state = reduce(State::default(), set("max_level", "FL120"));
+        let next = reduce(state.clone(), set("max_level", "FLx"));
+        assert_eq!(next.settings.max_level, 120);
+    }
+
+    #[test]
+    fn invert_twice() {
+        let all = ["A", "B", "C", "B"].map(String::from).to_vec();
+        let mut state = State::default();
+        state.settings.loa.insert("A".to_string());
+        state.settings.rat = vec!["C".to_string()];
+
+        let once = reduce(state.clone(), Action::InvertLoa { all: all.clone() });
+        let once = reduce(once, Action::InvertRat { all: all.clone() });
+        assert_eq!(
+            once.settings.loa,
+            HashSet::from(["B", "C"].map(String::from))
+        );
+        assert_eq!(once.settings.rat, ["A", "B"].map(String::from));
+
+        let twice = reduce(once, Action::InvertLoa { all: all.clone() });
+        let twice = reduce(twice, Action::InvertRat { all });
+        assert_eq!(twice.settings.loa, state.settings.loa);
+        assert_eq!(twice.settings.rat, state.settings.rat);
+    }
+
+    #[test]
+    fn rat_from_invalid() {
+        let state = reduce(State::default(), set("rat_from", "2023-02-28"));
+        assert_eq!(state.settings.rat_from.as_deref(), Some("2023-02-28"));
+
+        for value in ["2023-02-31", "not-a-date", "2023-13-01"] {
+            let next = reduce(state.clone(), set("rat_from", value));
+            assert_eq!(next.settings.rat_from, state.settings.rat_from);
+        }
+    }
+
+    #[test]
+    fn migrate_renamed() {
+        let value = serde_json::to_value(Settings {
+            loa: HashSet::from(["OLD LOA", "LOA"].map(String::from)),
+            rat: ["OLD RAT", "RAT"].map(String::from).to_vec(),
+            ..Settings::default()
+        })
+        .unwrap();
+        let renames = [("OLD LOA", "NEW LOA"), ("OLD RAT", "RAT")];
+
+        let settings = migrate(value, &renames);
+        assert_eq!(
+            settings.loa,
+            HashSet::from(["NEW LOA", "LOA"].map(String::from))
+        );
+        assert_eq!(settings.rat, ["RAT"].map(String::from));
+    }
+
+    #[test]
+    fn migrate_v0() {
+        // Settings saved before the schema was versioned
+        let value = json!({
+            "atz": "Ctr",
+            "ils": "ClassD",
+            "unlicensed": null,
+            "microlight": null,
+            "gliding": "Gliding",
+            "home": "LASHAM",
+            "hirta_gvs": null,
+            "obstacle": null,
+            "max_level": 195,
+            "radio": true,
+            "format": "OpenAir",
+            "overlay": "FL195",
+            "loa": ["OLD RAZ"],
+            "rat": ["ROYAL WEDDING"],
+            "wave": ["EDEN WAVE"]
+        });
+        let settings = migrate(value.clone(), &[("OLD RAZ", "CAMBRIDGE RAZ")]);
+
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.atz, AirType::Ctr);
+        assert_eq!(settings.ils, Some(AirType::ClassD));
+        assert_eq!(settings.home.as_deref(), Some("LASHAM"));
+        assert_eq!(settings.max_level, 195);
+        assert_eq!(settings.overlay, HashSet::from([Overlay::FL195]));
+        assert_eq!(settings.loa, HashSet::from(["CAMBRIDGE RAZ".to_string()]));
+        assert_eq!(settings.rat, ["ROYAL WEDDING"]);
+        assert_eq!(settings.wave, HashSet::from(["EDEN WAVE".to_string()]));
+
+        // Missing later options take their defaults
+        assert_eq!(settings.matz, Some(AirType::Matz));
+        assert_eq!(settings.freq_decimals, 3);
+
+        // No overlay
+        let mut value = value;
+        value["overlay"] = Value::Null;
+        assert!(migrate(value, SELECTION_RENAMES).overlay.is_empty());
+    }
+
+    #[test]
+    fn stale_selection() {
+        let mut state = State::default();
+        state.settings.loa = HashSet::from(["STALE", "LOA"].map(String::from));
+        state.settings.rat = ["RAT", "STALE"].map(String::from).to_vec();
+
+        let next = reduce(
+            state.clone(),
+            Action::RenameActiveSelection {
+                loa: vec!["LOA".to_string()],
+                rat: vec!["RAT".to_string()],
+                wave: vec![],
+            },
+        );
+        assert_eq!(next.settings.loa, HashSet::from(["LOA".to_string()]));
+        assert_eq!(next.settings.rat, ["RAT"].map(String::from));
+        assert_eq!(next.undo, vec![state.settings]);
+    }
+
+    #[test]
+    fn data_release() {
+        let state = reduce(State::default(), set("data_release", "2026-10-01"));
+        assert_eq!(state.settings.data_release.as_deref(), Some("2026-10-01"));
+
+        let query = state.settings.to_query_string();
+        assert_eq!(Settings::from_query_string(&query), state.settings);
+
+        for value in ["../yaixm", "a/b", "2026 10", &"a".repeat(41)] {
+            let next = reduce(state.clone(), set("data_release", value));
+            assert_eq!(next.settings.data_release, state.settings.data_release);
+            assert!(state
+                .settings
+                .clone()
+                .try_apply("data_release", value)
+                .is_err());
+        }
+
+        let next = reduce(state.clone(), set("data_release", "latest"));
+        assert_eq!(next.settings.data_release, None);
+    }
+
+    #[test]
+    fn release_fallback() {
+        let mut state = reduce(State::default(), set("data_release", "2026-10-01"));
+        assert!(!state.release_fallback());
+
+        state.loaded_release = Some(("2026-10-01T00:00:00Z".to_string(), "abc123".to_string()));
+        assert!(!state.release_fallback());
+
+        state.loaded_release = Some(("2026-11-26T00:00:00Z".to_string(), "def456".to_string()));
+        assert!(state.release_fallback());
+    }
+
+    #[test]
+    fn sorted_sets() {
+        let settings = |names: [&str; 3]| {
+            let mut settings = Settings::default();
+            for name in names {
+                settings.loa.insert(name.to_string());
+                settings.wave.insert(name.to_string());
+                settings.exclude.insert(name.to_string());
+                settings.gliding_sites.insert(name.to_string());
+            }
+            settings
+        };
+        let typed = |types: [AirType; 3], overlays: [Overlay; 3]| {
+            let mut settings = settings(["A", "B", "C"]);
+            for air_type in types {
+                settings.types.insert(air_type, false);
+                settings.type_caps.insert(air_type, 65);
+            }
+            settings.overlay.extend(overlays);
+            settings
+        };
+        let first = serde_json::to_string(&settings(["B", "C", "A"])).unwrap();
+        let second = serde_json::to_string(&settings(["C", "A", "B"])).unwrap();
+        assert_eq!(first, second);
+        assert!(first.contains(r#""loa":["A","B","C"]"#));
+
+        let first = serde_json::to_string(&typed(
+            [AirType::Prohibited, AirType::ClassA, AirType::Danger],
+            [Overlay::AtzDz, Overlay::FL195, Overlay::Custom(30)],
+        ))
+        .unwrap();
+        let second = serde_json::to_string(&typed(
+            [AirType::Danger, AirType::Prohibited, AirType::ClassA],
+            [Overlay::Custom(30), Overlay::AtzDz, Overlay::FL195],
+        ))
+        .unwrap();
+        assert_eq!(first, second);
+        assert!(first.contains(r#""overlay":["FL195","AtzDz",{"Custom":30}]"#));
+
+        let unsorted = first.replace(r#""loa":["A","B","C"]"#, r#""loa":["C","A","B"]"#);
+        let settings = serde_json::from_str::<Settings>(&unsorted).unwrap();
+        assert_eq!(serde_json::to_string(&settings).unwrap(), first);
+    }
+
+    #[test]
+    fn loa_category() {
+        let category = |checked| Action::SetLoaCategory {
+            category: "Gliding".to_string(),
+            checked,
+            members: vec!["CAMBRIDGE RAZ".to_string(), "SHAWBURY".to_string()],
+        };
+        let loa = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+
+        // Partly selected category, plus an LOA from another category
+        let state = [("SHAWBURY", true), ("BRIZE", true)].into_iter().fold(
+            State::default(),
+            |state, (name, checked)| {
+                reduce(
+                    state,
+                    Action::SetLoa {
+                        name: name.to_string(),
+                        checked,
+                    },
+                )
+            },
+        );
+
+        let state = reduce(state, category(true));
+        assert_eq!(
+            state.settings.loa,
+            loa(&["BRIZE", "CAMBRIDGE RAZ", "SHAWBURY"])
+        );
+
+        let state = reduce(state, category(false));
+        assert_eq!(state.settings.loa, loa(&["BRIZE"]));
+    }
+
+    #[test]
+    fn replace_selection() {
+        let names = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<String>>()
+        };
+
+        let mut settings = Settings::default();
+        settings.loa.extend(names(&["BRIZE", "SHAWBURY"]));
+        settings.wave.extend(names(&["EDEN WAVE"]));
+        let state = State {
+            settings,
+            ..State::default()
+        };
+
+        let state = reduce(
+            state,
+            Action::ReplaceLoa {
+                names: names(&["CAMBRIDGE RAZ"]).into_iter().collect(),
+            },
+        );
+        assert_eq!(
+            state.settings.loa,
+            names(&["CAMBRIDGE RAZ"]).into_iter().collect()
+        );
+        assert_eq!(state.undo.len(), 1);
+
+        let state = reduce(
+            state,
+            Action::ReplaceRat {
+                names: names(&["B", "A", "B"]),
+            },
+        );
+        assert_eq!(state.settings.rat, ["B", "A"]);
+        assert_eq!(state.undo.len(), 2);
+
+        let state = reduce(
+            state,
+            Action::ReplaceWave {
+                names: HashSet::new(),
+            },
+        );
+        assert!(state.settings.wave.is_empty());
+        assert_eq!(state.undo.len(), 3);
+    }
+
+    #[test]
+    fn move_rat() {
+        let rat = |name: &str| Action::SetRat {
+            name: name.to_string(),
+            checked: true,
+        };
+        let move_rat = |name: &str, to_index| Action::MoveRat {
+            name: name.to_string(),
+            to_index,
+        };
+        let state = ["A", "B", "C"]
+            .into_iter()
+            .fold(State::default(), |state, name| reduce(state, rat(name)));
+
+        // Up and down
+        let state = reduce(state, move_rat("C", 1));
+        assert_eq!(state.settings.rat, ["A", "C", "B"]);
+        let state = reduce(state, move_rat("A", 1));
+        assert_eq!(state.settings.rat, ["C", "A", "B"]);
+
+        // Past the end
+        let state = reduce(state, move_rat("C", 10));
+        assert_eq!(state.settings.rat, ["A", "B", "C"]);
+
+        // Unselected RAT is ignored
+        let next = reduce(state.clone(), move_rat("D", 0));
+        assert_eq!(next.settings, state.settings);
+    }
+
+    #[test]
+    fn wave_range() {
+        let all = ["SCOTLAND EAST", "SCOTLAND WEST", "WALES NORTH"]
+            .map(String::from)
+            .to_vec();
+        let range = |prefix: &str, checked| Action::SetWaveRange {
+            prefix: prefix.to_string(),
+            checked,
+            all: all.clone(),
+        };
+
+        let state = reduce(State::default(), range("SCOTLAND", true));
+        let mut wave = state.settings.wave.iter().collect::<Vec<&String>>();
+        wave.sort();
+        assert_eq!(wave, ["SCOTLAND EAST", "SCOTLAND WEST"]);
+
+        let state = reduce(state, range("WALES", true));
+        let state = reduce(state, range("SCOTLAND", false));
+        assert_eq!(
+            state.settings.wave,
+            HashSet::from(["WALES NORTH".to_string()])
+        );
+    }
+
+    #[test]
+    fn gliding_sites() {
+        let site = |name: &str, checked| Action::SetGlidingSite {
+            name: name.to_string(),
+            checked,
+        };
+
+        let state = reduce(State::default(), site("LASHAM", true));
+        let state = reduce(state, site("NYMPSFIELD", true));
+        let state = reduce(state, site("LASHAM", false));
+        assert_eq!(
+            state.settings.gliding_sites,
+            HashSet::from(["NYMPSFIELD".to_string()])
+        );
+
+        let state = reduce(state, Action::ClearGlidingSites);
+        assert!(state.settings.gliding_sites.is_empty());
+    }
+
+    #[test]
+    fn stacked_overlays() {
+        let overlay = |name: &str| Action::SetOverlay {
+            name: name.to_string(),
+            checked: true,
+        };
+
+        let state = reduce(State::default(), overlay("fl195"));
+        let state = reduce(state, overlay("atzdz"));
+        assert_eq!(
+            state.settings.overlay,
+            HashSet::from([Overlay::FL195, Overlay::AtzDz])
+        );
+        assert_eq!(
+            state.settings.to_query_string(),
+            "overlay=fl195&overlay=atzdz"
+        );
+
+        let state = reduce(state, Action::ClearOverlay);
+        assert!(state.settings.overlay.is_empty());
+    }
+
+    #[test]
+    fn toggle_radio() {
+        let state = reduce(State::default(), Action::ToggleRadio);
+        assert!(state.settings.radio);
+        let state = reduce(state, Action::ToggleRadio);
+        assert_eq!(state.settings, Settings::default());
+
+        // Setting a known value is idempotent
+        let state = reduce(state, Action::SetRadio(true));
+        let state = reduce(state, Action::SetRadio(true));
+        assert!(state.settings.radio);
+        let state = reduce(state, Action::SetRadio(false));
+
+        // Query string values other than yes/no are ignored
+        let state = reduce(state, set("radio", "yes"));
+        let next = reduce(state.clone(), set("radio", "maybe"));
+        assert!(next.settings.radio);
+    }
+
+    #[test]
+    fn touched_settings() {
+        let state = reduce(State::default(), set("atz", "classd"));
+        assert_eq!(state.touched, HashSet::from(["atz".to_string()]));
+
+        // Touched options keep their value when merging
+        let overrides = PartialSettings {
+            atz: Some(AirType::Ctr),
+            max_level: Some(195),
+            ..PartialSettings::default()
+        };
+        let merged = merge_untouched(&state.settings, &overrides, &state.touched);
+        assert_eq!(merged.atz, AirType::ClassD);
+        assert_eq!(merged.max_level, 195);
+
+        // Explicitly kept option
+        let state = reduce(
+            state,
+            Action::TouchSetting {
+                name: "max_level".to_string(),
+            },
+        );
+        let merged = merge_untouched(&state.settings, &overrides, &state.touched);
+        assert_eq!(merged.max_level, 660);
+
+        let state = reduce(state, Action::Reset);
+        assert!(state.touched.is_empty());
+    }
+
+    #[test]
+    fn selected_names() {
+        let mut settings = Settings::default();
+        settings.loa.insert("SHAWBURY".to_string());
+        settings.loa.insert("CAMBRIDGE RAZ".to_string());
+        settings.rat = vec!["ROYAL WEDDING".to_string(), "AIR SHOW".to_string()];
+        settings.wave.insert("EDEN WAVE".to_string());
+
+        assert_eq!(
+            settings.selected_names(),
+            [
+                (SelectionKind::Loa, "CAMBRIDGE RAZ".to_string()),
+                (SelectionKind::Loa, "SHAWBURY".to_string()),
+                (SelectionKind::Rat, "AIR SHOW".to_string()),
+                (SelectionKind::Rat, "ROYAL WEDDING".to_string()),
+                (SelectionKind::Wave, "EDEN WAVE".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn describe() {
+        assert_eq!(
+            Settings::default().describe(),
+            "CTR as ATZ, no maximum level, radio frequencies off, OpenAir format."
+        );
+
+        let mut settings = Settings {
+            atz: AirType::ClassD,
+            max_level: 195,
+            min_level: 65,
+            radio: true,
+            rat: vec!["ROYAL WEDDING".to_string()],
+            format: Format::Kml,
+            ..Settings::default()
+        };
+        settings.loa.insert("CAMBRIDGE RAZ".to_string());
+        settings.loa.insert("SHAWBURY".to_string());
+        settings.wave.insert("EDEN WAVE".to_string());
+        assert_eq!(
+            settings.describe(),
+            "Class D as ATZ, up to FL195, from FL65, radio frequencies on, \
+             2 LOAs, 1 RAT, 1 wave box, KML format."
+        );
+    }
+
+    #[test]
+    fn canonical_eq() {
+        let rat = |names: [&str; 2]| Settings {
+            rat: names.map(String::from).to_vec(),
+            ..Settings::default()
+        };
+        assert_ne!(rat(["A", "B"]), rat(["B", "A"]));
+        assert!(rat(["A", "B"]).canonical_eq(&rat(["B", "A"])));
+        assert!(!rat(["A", "B"]).canonical_eq(&rat(["A", "C"])));
+
+        // Profile saved with RATs in a different order is still active
+        let state = State {
+            settings: rat(["A", "B"]),
+            ..State::default()
+        };
+        let state = reduce(
+            state,
+            Action::SaveProfile {
+                name: "club".to_string(),
+            },
+        );
+        let state = State {
+            settings: rat(["B", "A"]),
+            ..state
+        };
+        assert_eq!(state.active_profile(), Some("club".to_string()));
+    }
+
+    #[test]
+    fn diff_settings() {
+        let stored = Settings::default();
+        let mut link = Settings {
+            atz: AirType::ClassD,
+            ..Settings::default()
+        };
+        link.loa.insert("CAMBRIDGE RAZ".to_string());
+
+        let changes = diff(&stored, &link);
+        assert_eq!(
+            changes,
+            [
+                SettingChange::Changed {
+                    name: "atz".to_string(),
+                    before: Some("ctr".to_string()),
+                    after: Some("classd".to_string()),
+                },
+                SettingChange::Added {
+                    name: "loa".to_string(),
+                    value: "CAMBRIDGE RAZ".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            changes.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            ["atz: ctr to classd", "loa: added CAMBRIDGE RAZ"]
+        );
+        assert!(diff(&link, &link).is_empty());
+    }
+
+    #[test]
+    fn set_many() {
+        let entries = [
+            ("atz", "ctr"),
+            ("gliding", "gsec"),
+            ("max_level", "195"),
+            ("format", "cup"),
+        ];
+
+        let batch = reduce(
+            State::default(),
+            Action::SetMany {
+                entries: entries
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+            },
+        );
+        let sequence = entries
+            .iter()
+            .fold(State::default(), |state, (name, value)| {
+                reduce(state, set(name, value))
+            });
+
+        assert_eq!(batch.settings, sequence.settings);
+        assert_eq!(batch.touched, sequence.touched);
+        assert_eq!(batch.undo.len(), 1);
+    }
+
+    #[test]
+    fn type_caps() {
+        let cap = |level| Action::SetTypeCap {
+            air_type: AirType::Gliding,
+            level,
+        };
+
+        let state = reduce(State::default(), cap(Some(700)));
+        assert_eq!(state.settings.type_caps.get(&AirType::Gliding), Some(&660));
+        assert_eq!(state.settings.to_query_string(), "cap=gsec%3A660");
+
+        let state = reduce(state, cap(None));
+        assert!(state.settings.type_caps.is_empty());
+    }
+
+    #[test]
+    fn type_sync() {
+        let enable = |air_type, enabled| Action::SetTypeEnabled { air_type, enabled };
+
+        // Excluding a type turns off features mapped to it
+        let state = reduce(State::default(), set("gliding", "gsec"));
+        let state = reduce(state, enable(AirType::Gliding, false));
+        assert_eq!(state.settings.gliding, None);
+        assert_eq!(state.settings.types.get(&AirType::Gliding), Some(&false));
+        assert_eq!(state.settings.to_query_string(), "disable=gsec");
+
+        // Mapping a feature to the type includes it again
+        let state = reduce(state, set("gliding", "gsec"));
+        assert!(state.settings.types.is_empty());
+
+        // Query string disable goes through the same path
+        let state = reduce(state, set("disable", "matz"));
+        assert_eq!(state.settings.matz, None);
+        let state = reduce(state, enable(AirType::Matz, true));
+        assert_eq!(state.settings.types, HashMap::new());
+    }
+
+    #[test]
+    fn import_code() {
+        let settings = Settings {
+            max_level: 195,
+            ..Settings::default()
+        };
+        let text = settings.to_base64();
+        let state = reduce(State::default(), Action::ImportSettings { text });
+        assert_eq!(state.settings, settings);
+        assert_eq!(state.import_error, None);
+
+        let text = "not a code".to_string();
+        let next = reduce(state.clone(), Action::ImportSettings { text });
+        assert_eq!(next.settings, settings);
+        assert!(next.import_error.is_some());
+    }
+
+    #[test]
+    fn import_json() {
+        let mut settings = Settings {
+            max_level: 125,
+            ..Settings::default()
+        };
+        settings.loa.insert("CAMBRIDGE RAZ".to_string());
+        let text = settings.to_pretty_json();
+        let state = reduce(State::default(), Action::ImportSettings { text });
+        assert_eq!(state.settings, settings);
+        assert_eq!(state.import_error, None);
+        assert_eq!(state.undo.len(), 1);
+
+        // Malformed JSON leaves settings unchanged, with an error
+        for text in ["{\"max_level\": 1", "{\"atz\": \"Nowhere\"}"] {
+            let text = text.to_string();
+            let next = reduce(state.clone(), Action::ImportSettings { text });
+            assert_eq!(next.settings, settings);
+            assert_eq!(next.undo, state.undo);
+            assert!(next.import_error.is_some());
+
+            // Cleared by the next good import
+            let text = Settings::default().to_pretty_json();
+            let next = reduce(next, Action::ImportSettings { text });
+            assert_eq!(next.settings, Settings::default());
+            assert_eq!(next.import_error, None);
+        }
+    }
+
+    #[test]
+    fn merge_settings() {
+        let mut base = Settings::default();
+        base.loa.insert("CAMBRIDGE RAZ".to_string());
+
+        let overrides = PartialSettings {
+            max_level: Some(195),
+            loa: Some(HashSet::from(["SHAWBURY".to_string()])),
+            ..PartialSettings::default()
+        };
+        let merged = merge(&base, &overrides);
+        assert_eq!(merged.max_level, 195);
+        assert_eq!(merged.atz, base.atz);
+        assert_eq!(
+            merged.loa,
+            HashSet::from(["CAMBRIDGE RAZ".to_string(), "SHAWBURY".to_string()])
+        );
+
+        // Merge action, with a bad override leaving settings unchanged
+        let state = State {
+            settings: base,
+            ..State::default()
+        };
+        let text = serde_json::to_string(&overrides).unwrap();
+        let state = reduce(state, Action::MergeSettings { text });
+        assert_eq!(state.settings, merged);
+        assert_eq!(state.undo.len(), 1);
+
+        let text = r#"{"max_level": "high"}"#.to_string();
+        let next = reduce(state.clone(), Action::MergeSettings { text });
+        assert_eq!(next.settings, merged);
+        assert!(next.import_error.is_some());
+    }
+
+    #[test]
+    fn session_round_trip() {
+        let state = reduce(State::default(), set("atz", "classd"));
+        let state = reduce(
+            state,
+            Action::SaveProfile {
+                name: "club".to_string(),
+            },
+        );
+        assert_eq!(state.undo.len(), 1);
+
+        let json = state.to_session_json();
+        assert_eq!(State::from_session_json(&json).unwrap(), state);
+
+        // Restored session can be undone
+        let restored = reduce(State::default(), Action::RestoreSession { text: json });
+        assert_eq!(restored.settings.atz, AirType::ClassD);
+        assert_eq!(restored.profiles, state.profiles);
+        let undone = reduce(restored, Action::Undo);
+        assert_eq!(undone.settings, Settings::default());
+
+        let bad = reduce(
+            state.clone(),
+            Action::RestoreSession {
+                text: "not a session".to_string(),
+            },
+        );
+        assert_eq!(bad.settings, state.settings);
+        assert!(bad.import_error.is_some());
+    }
+
+    // Action trace, debug builds only
+    #[cfg(debug_assertions)]
+    mod trace {
+        use super::*;
+        use std::cell::RefCell;
+
+        // Logger keeping messages for the current test thread
+        struct TestLogger;
+
+        thread_local! {
+            static LOGGED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+        }
+
+        impl log::Log for TestLogger {
+            fn enabled(&self, _metadata: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                LOGGED.with(|logged| logged.borrow_mut().push(record.args().to_string()));
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: TestLogger = TestLogger;
+
+        // Log messages from f
+        fn capture_log(f: impl FnOnce()) -> Vec<String> {
+            let _ = log::set_logger(&LOGGER);
+            log::set_max_level(log::LevelFilter::Debug);
+
+            LOGGED.with(|logged| logged.borrow_mut().clear());
+            f();
+            LOGGED.with(|logged| logged.take())
+        }
+
+        #[test]
+        fn trace_set() {
+            let logged = capture_log(|| {
+                reduce(State::default(), set("atz", "classd"));
+            });
+
+            assert_eq!(
+                logged,
+                vec![concat!(
+                    r#"Set { name: "atz", value: "classd" } -> "#,
+                    r#"[Changed { name: "atz", before: Some("ctr"), after: Some("classd") }]"#
+                )]
+            );
+        }
+
+        #[test]
+        fn trace_every_action() {
+            let logged = capture_log(|| {
+                let state = reduce(State::default(), set("radio", "yes"));
+                let state = reduce(state, Action::Undo);
+                let state = reduce(state, Action::Redo);
+                reduce(state, Action::MarkSaved);
+            });
+
+            assert_eq!(logged.len(), 4);
+            assert!(logged[1].starts_with("Undo -> [Changed { name: \"radio\""));
+            assert_eq!(logged[3], "MarkSaved -> []");
+        }
+    }
+
+    #[test]
+    fn query_round_trip() {
+        let mut settings = Settings {
+            atz: AirType::Ctr,
+            matz: None,
+            gliding: Some(AirType::Gliding),
+            home: Some("LASHAM".to_string()),
+            home_coords: Some((51.2, -1.03)),
+            max_level: 125,
+            min_level: 20,
+            radio: true,
+            wave_clip: WaveClip::Keep,
+            name_prefix: Some("UK".to_string()),
+            max_features: Some(500),
+            format: Format::Kml,
+            rat: vec!["ZULU".to_string(), "ALPHA".to_string()],
+            ..Settings::default()
+        };
+        settings
+            .loa
+            .extend(["CAMBRIDGE RAZ".to_string(), "SHAWBURY".to_string()]);
+        settings.wave.insert("EDEN WAVE".to_string());
+        settings.exclude.insert("D123 IMBER".to_string());
+        settings.gliding_sites.insert("NYMPSFIELD".to_string());
+        settings
+            .overlay
+            .extend([Overlay::FL195, Overlay::Custom(30)]);
+        settings.types.insert(AirType::Danger, false);
+        settings.type_caps.insert(AirType::ClassD, 65);
+
+        let query = settings.to_query_string();
+        assert_eq!(Settings::from_query_string(&query), settings);
+        assert_eq!(
+            Settings::from_query_string(&format!("?{}", query)),
+            settings
+        );
+        assert!(query.contains("rat=ZULU&rat=ALPHA"));
+        assert!(query.contains("loa=CAMBRIDGE+RAZ&loa=SHAWBURY"));
+
+        let query = settings.to_full_query_string();
+        assert_eq!(Settings::from_query_string(&query), settings);
+    }
+
+    #[test]
+    fn pretty_json() {
+        let settings = |names: [&str; 3]| {
+            let mut settings = Settings {
+                max_level: 125,
+                ..Settings::default()
+            };
+            for name in names {
+                settings.loa.insert(name.to_string());
+                settings.wave.insert(name.to_string());
+            }
+            settings
+        };
+        let text = settings(["B", "C", "A"]).to_pretty_json();
+        assert_eq!(text, settings(["C", "A", "B"]).to_pretty_json());
+        assert!(text.contains("\"loa\": [\n    \"A\",\n    \"B\",\n    \"C\"\n  ]"));
+
+        let parsed: Settings = serde_json::from_str(&text).unwrap();
+        assert_eq!(parsed, settings(["A", "B", "C"]));
+    }
+
+    #[test]
+    fn query_defaults_omitted() {
+        assert_eq!(Settings::default().to_query_string(), "");
+        assert_eq!(Settings::from_query_string(""), Settings::default());
+
+        let settings = Settings {
+            radio: !Settings::default().radio,
+            ..Settings::default()
+        };
+        let query = settings.to_query_string();
+        assert_eq!(query.split('&').count(), 1);
+        assert!(query.starts_with("radio="));
+        assert_eq!(Settings::from_query_string(&query), settings);
+    }
+
+    #[test]
+    fn undo_redo() {
+        let first = reduce(State::default(), set("max_level", "125"));
+        let second = reduce(first.clone(), set("radio", "yes"));
+        assert_eq!(
+            second.undo,
+            vec![Settings::default(), first.settings.clone()]
+        );
+
+        let undone = reduce(second.clone(), Action::Undo);
+        assert_eq!(undone.settings, first.settings);
+        assert_eq!(undone.redo, vec![second.settings.clone()]);
+
+        let redone = reduce(undone.clone(), Action::Redo);
+        assert_eq!(redone.settings, second.settings);
+        assert_eq!(redone.undo, second.undo);
+        assert!(redone.redo.is_empty());
+
+        // New edit clears redo history
+        let edited = reduce(undone, set("max_level", "105"));
+        assert!(edited.redo.is_empty());
+        assert_eq!(reduce(edited.clone(), Action::Redo), edited);
+
+        // Nothing to undo
+        let state = State::default();
+        assert_eq!(reduce(state.clone(), Action::Undo), state);
+    }
+
+    #[test]
+    fn undo_limit() {
+        let mut state = State::default();
+        for level in 0..=MAX_HISTORY {
+            let level = 5 * (level + 1);
+            state = reduce(state, set("max_level", &level.to_string()));
+        }
+        assert_eq!(state.undo.len(), MAX_HISTORY);
+
+        // Oldest entries dropped first
+        assert_eq!(state.undo.front().unwrap().max_level, 5);
+        assert_eq!(state.undo.back().unwrap().max_level, 250);
+        assert_eq!(state.settings.max_level, 255);
+    }
+
+    #[test]
+    fn reset() {
+        let mut state = reduce(State::default(), set("max_level", "125"));
+        state = reduce(state, set("format", "kml"));
+        state = reduce(
+            state,
+            Action::SetLoa {
+                name: "CAMBRIDGE RAZ".to_string(),
+                checked: true,
+            },
+        );
+        assert_ne!(state.settings, Settings::default());
+
+        let reset = reduce(state.clone(), Action::Reset);
+        assert_eq!(reset.settings, State::default().settings);
+        assert!(reset.touched.is_empty());
+
+        // Reset can be undone
+        assert_eq!(reduce(reset, Action::Undo).settings, state.settings);
+    }
+
+    #[test]
+    fn profiles() {
+        let save = |state, name: &str| {
+            reduce(
+                state,
+                Action::SaveProfile {
+                    name: name.to_string(),
+                },
+            )
+        };
+        let load = |state, name: &str| {
+            reduce(
+                state,
+                Action::LoadProfile {
+                    name: name.to_string(),
+                },
+            )
+        };
+
+        let club = reduce(State::default(), set("max_level", "125"));
+        let state = save(club.clone(), "club");
+        assert_eq!(state.settings, club.settings);
+        assert_eq!(state.undo, club.undo);
+
+        // Saving again with the same name replaces the profile, profiles
+        // are kept in name order
+        let state = reduce(state, set("radio", "yes"));
+        let state = save(state, "club");
+        let state = save(state, "alpha");
+        let names = state
+            .profiles
+            .iter()
+            .map(|profile| profile.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, ["alpha", "club"]);
+
+        // Round trip
+        let expected = state.settings.clone();
+        let state = reduce(state, Action::Reset);
+        let state = load(state, "club");
+        assert_eq!(state.settings, expected);
+
+        // Missing profile leaves settings and history unchanged
+        let next = load(state.clone(), "missing");
+        assert_eq!(next, state);
+    }
+
+    #[test]
+    fn airtype_case() {
+        for air_type in AirType::ALL {
+            let value = airtype_value(air_type);
+            for value in [
+                value.to_uppercase(),
+                format!(" {} ", value),
+                format!("\t{}\n", value.to_uppercase()),
+            ] {
+                assert_eq!(get_airtype(&value), Some(air_type));
+            }
+        }
+        assert_eq!(get_airtype("CTR"), Some(AirType::Ctr));
+        assert_eq!(get_airtype(" ClassD "), Some(AirType::ClassD));
+        assert_eq!(get_airtype("C T R"), None);
+        assert_eq!(get_airtype(""), None);
+
+        let state = reduce(State::default(), set("atz", "CTR"));
+        assert_eq!(state.settings.atz, AirType::Ctr);
+    }
+
+    #[test]
+    fn airtype_keys() {
+        let keys = [
+            ("awy", AirType::Awy),
+            ("classa", AirType::ClassA),
+            ("classb", AirType::ClassB),
+            ("classc", AirType::ClassC),
+            ("classd", AirType::ClassD),
+            ("classe", AirType::ClassE),
+            ("classf", AirType::ClassF),
+            ("classg", AirType::ClassG),
+            ("cta", AirType::Cta),
+            ("ctr", AirType::Ctr),
+            ("danger", AirType::Danger),
+            ("gsec", AirType::Gliding),
+            ("matz", AirType::Matz),
+            ("other", AirType::Other),
+            ("prohibited", AirType::Prohibited),
+            ("restricted", AirType::Restricted),
+            ("rmz", AirType::Rmz),
+            ("tmz", AirType::Tmz),
+        ];
+        for (key, air_type) in keys {
+            assert_eq!(get_airtype(key), Some(air_type));
+            assert_eq!(airtype_value(air_type), key);
+        }
+
+        // Every type has a key
+        assert_eq!(keys.len(), AirType::ALL.len());
+    }
+
+    #[test]
+    fn airtype_order() {
+        let mut types = vec![
+            AirType::Other,
+            AirType::ClassG,
+            AirType::Danger,
+            AirType::Matz,
+            AirType::Ctr,
+            AirType::ClassE,
+            AirType::ClassA,
+        ];
+        types.sort();
+        assert_eq!(
+            types,
+            [
+                AirType::ClassA,
+                AirType::ClassE,
+                AirType::Ctr,
+                AirType::Danger,
+                AirType::Matz,
+                AirType::ClassG,
+                AirType::Other,
+            ]
+        );
+    }
+
+    #[test]
+    fn airtype_strict() {
+        assert_eq!(get_airtype_strict("ClassD"), Ok(AirType::ClassD));
+        assert_eq!(get_airtype_strict(" gsec "), Ok(AirType::Gliding));
+        for value in ["classx", "gliding", ""] {
+            assert_eq!(
+                get_airtype_strict(value),
+                Err(UnknownAirType(value.to_string()))
+            );
+            assert_eq!(get_airtype(value), None);
+        }
+        assert_eq!(
+            UnknownAirType("classx".to_string()).to_string(),
+            "unknown airspace type \"classx\""
+        );
+
+        // Import path rejects unknown types rather than using a default
+        let mut settings = Settings::default();
+        assert_eq!(
+            settings.try_apply("atz", "classx"),
+            Err(SettingsError::InvalidValue {
+                name: "atz".to_string(),
+                value: "classx".to_string()
+            })
+        );
+        assert!(settings.try_apply("disable", "nothing").is_err());
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn try_apply_unknown_name() {
+        let mut settings = Settings::default();
+        let err = settings.try_apply("max_altitude", "195").unwrap_err();
+        assert_eq!(err, SettingsError::UnknownName("max_altitude".to_string()));
+        assert_eq!(err.to_string(), "unknown setting \"max_altitude\"");
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn try_apply_invalid_value() {
+        for (name, value) in [
+            ("max_level", "high"),
+            ("radio", "maybe"),
+            ("format", "shapefile"),
+            ("freq_decimals", "4"),
+            ("overlay", "fl999x"),
+            ("cap", "danger"),
+            ("home_coords", "north"),
+            ("matz", "classx"),
+            ("rat_from", "2026-02-30"),
+        ] {
+            let mut settings = Settings::default();
+            let err = settings.try_apply(name, value).unwrap_err();
+            assert_eq!(
+                err,
+                SettingsError::InvalidValue {
+                    name: name.to_string(),
+                    value: value.to_string()
+                }
+            );
+            assert_eq!(settings, Settings::default());
+        }
+
+        let err = Settings::default().try_apply("radio", "maybe").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value \"maybe\" for setting \"radio\""
+        );
+    }
+
+    #[test]
+    fn try_apply_ils() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.try_apply("ils", "classd"), Ok(()));
+        assert_eq!(settings.ils, Some(AirType::ClassD));
+
+        // "atz" selects ILS feathers as ATZ
+        assert_eq!(settings.try_apply("ils", "atz"), Ok(()));
+        assert_eq!(settings.ils, None);
+
+        assert!(settings.try_apply("ils", "atzx").is_err());
+        assert!(settings.try_apply("matz", "atz").is_err());
+    }
+
+    #[test]
+    fn home_coords() {
+        assert_eq!(parse_coords("51.2,-1.03"), Some((51.2, -1.03)));
+        assert_eq!(parse_coords(" -90 , 180 "), Some((-90.0, 180.0)));
+        for value in [
+            "91,0",
+            "0,-180.5",
+            "51.2",
+            "51.2,",
+            "a,b",
+            "51.2;-1.03",
+            "NaN,0",
+            "inf,0",
+            "",
+        ] {
+            assert_eq!(parse_coords(value), None, "{}", value);
+        }
+
+        let coords = |lat, lon| Action::SetHomeCoords { lat, lon };
+        let state = reduce(State::default(), coords(51.2, -1.03));
+        assert_eq!(state.settings.home_coords, Some((51.2, -1.03)));
+
+        // Out of range or garbage leaves the previous value
+        for (lat, lon) in [
+            (90.5, 0.0),
+            (0.0, 181.0),
+            (f64::NAN, 0.0),
+            (0.0, f64::INFINITY),
+        ] {
+            let next = reduce(state.clone(), coords(lat, lon));
+            assert_eq!(next.settings.home_coords, Some((51.2, -1.03)));
+            assert_eq!(next.undo, state.undo);
+        }
+        let next = reduce(state.clone(), set("home_coords", "200,0"));
+        assert_eq!(next.settings.home_coords, Some((51.2, -1.03)));
+
+        let next = reduce(state, Action::ClearHomeCoords);
+        assert_eq!(next.settings.home_coords, None);
+    }
+
+    #[test]
+    fn overlay_conflicts() {
+        let settings = |max_level, overlays: &[Overlay], format| Settings {
+            max_level,
+            overlay: overlays.iter().copied().collect(),
+            format,
+            ..Settings::default()
+        };
+
+        // No overlay, or overlays below the maximum level
+        assert_eq!(settings(105, &[], Format::Kml).overlay_conflicts(), None);
+        assert_eq!(
+            settings(195, &[Overlay::FL195, Overlay::FL105], Format::OpenAir).overlay_conflicts(),
+            None
+        );
+        assert_eq!(
+            settings(65, &[Overlay::AtzDz], Format::OpenAir).overlay_conflicts(),
+            None
+        );
+
+        // Overlay above the maximum level, first in overlay order reported
+        assert_eq!(
+            settings(65, &[Overlay::FL195, Overlay::Custom(80)], Format::OpenAir)
+                .overlay_conflicts()
+                .as_deref(),
+            Some("FL195 overlay is above the maximum level (FL65)")
+        );
+        assert_eq!(
+            settings(65, &[Overlay::Custom(80)], Format::OpenAirLk)
+                .overlay_conflicts()
+                .as_deref(),
+            Some("FL80 overlay is above the maximum level (FL65)")
+        );
+
+        // Overlay with a non-OpenAir format
+        assert_eq!(
+            settings(195, &[Overlay::FL105], Format::Csv)
+                .overlay_conflicts()
+                .as_deref(),
+            Some("Overlays are only included in OpenAir output")
+        );
+    }
+
+    #[test]
+    fn validate_warnings() {
+        let settings = Settings {
+            ils: Some(AirType::ClassG),
+            unlicensed: Some(AirType::ClassF),
+            microlight: Some(AirType::Other),
+            ..Settings::default()
+        };
+        assert_eq!(settings.validate(), vec![]);
+        assert_eq!(Settings::default().validate(), vec![]);
+
+        let settings = Settings {
+            ils: Some(AirType::Gliding),
+            unlicensed: Some(AirType::Danger),
+            microlight: Some(AirType::ClassA),
+            ..Settings::default()
+        };
+        let warning = |name: &str, value: &str| Warning {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        assert_eq!(
+            settings.validate(),
+            vec![
+                warning("ils", "gsec"),
+                warning("unlicensed", "danger"),
+                warning("microlight", "classa")
+            ]
+        );
+        assert_eq!(
+            warning("ils", "gsec").to_string(),
+            "unusual type \"gsec\" for setting \"ils\""
+        );
+    }
+
+    #[test]
+    fn exclude() {
+        let exclude = |name: &str, checked| Action::SetExclude {
+            name: name.to_string(),
+            checked,
+        };
+
+        let state = reduce(State::default(), exclude("D123 IMBER", true));
+        let state = reduce(state, exclude("D125 LARKHILL", true));
+        assert_eq!(
+            state.settings.exclude,
+            HashSet::from(["D123 IMBER", "D125 LARKHILL"].map(String::from))
+        );
+
+        let next = reduce(state.clone(), exclude("D125 LARKHILL", false));
+        assert_eq!(
+            next.settings.exclude,
+            HashSet::from(["D123 IMBER".to_string()])
+        );
+
+        // Excluded names survive a shared link
+        let query = state.settings.to_query_string();
+        assert!(query.contains("exclude=D123+IMBER"));
+        assert_eq!(
+            Settings::from_query_string(&query).exclude,
+            state.settings.exclude
+        );
+
+        let next = reduce(state, Action::ClearExclude);
+        assert!(next.settings.exclude.is_empty());
+    }
+
+    #[test]
+    fn matz_key() {
+        let state = reduce(State::default(), set("matz", "exclude"));
+        assert_eq!(state.settings.matz, None);
+
+        let state = reduce(state, set("matz", "classd"));
+        assert_eq!(state.settings.matz, Some(AirType::ClassD));
+
+        let state = reduce(state, set("matz", "matz"));
+        assert_eq!(state.settings.matz, Some(AirType::Matz));
+        // Default MATZ is omitted from shared links
+        assert!(!state.settings.to_query_string().contains("matz"));
+    }
+
+    #[test]
+    fn select_all() {
+        let names = |names: &[&str]| names.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+        let state = reduce(
+            State::default(),
+            Action::SetLoa {
+                name: "CAMBRIDGE RAZ".to_string(),
+                checked: true,
+            },
+        );
+        let state = reduce(
+            state,
+            Action::SelectAllLoa {
+                names: names(&["CAMBRIDGE RAZ", "DAVENTRY BOX"]),
+            },
+        );
+        assert_eq!(
+            state.settings.loa,
+            HashSet::from_iter(names(&["CAMBRIDGE RAZ", "DAVENTRY BOX"]))
+        );
+
+        // Existing RAT order is kept, without duplicates
+        let state = reduce(
+            state,
+            Action::SetRat {
+                name: "ROYAL WEDDING".to_string(),
+                checked: true,
+            },
+        );
+        let state = reduce(
+            state,
+            Action::SelectAllRat {
+                names: names(&["AIR TATTOO", "ROYAL WEDDING"]),
+            },
+        );
+        assert_eq!(state.settings.rat, names(&["ROYAL WEDDING", "AIR TATTOO"]));
+
+        let state = reduce(
+            state,
+            Action::SelectAllWave {
+                names: names(&["EDEN WAVE", "TALGARTH WAVE"]),
+            },
+        );
+        assert_eq!(
+            state.settings.wave,
+            HashSet::from_iter(names(&["EDEN WAVE", "TALGARTH WAVE"]))
+        );
+
+        let state = reduce(
+            state,
+            Action::SelectAllExclude {
+                names: names(&["D123 IMBER"]),
+            },
+        );
+        assert_eq!(
+            state.settings.exclude,
+            HashSet::from_iter(names(&["D123 IMBER"]))
+        );
+    }
+
+    #[test]
+    fn dirty() {
+        let state = State::default();
+        assert!(!state.is_dirty());
+
+        let state = reduce(state, set("radio", "yes"));
+        assert!(state.is_dirty());
+
+        let state = reduce(state, Action::MarkSaved);
+        assert!(!state.is_dirty());
+
+        // Undo back to the unsaved settings
+        let state = reduce(state, Action::Undo);
+        assert!(state.is_dirty());
+        let state = reduce(state, Action::Redo);
+        assert!(!state.is_dirty());
+    }
+
+    #[test]
+    fn set_overlay() {
+        let overlay = |name: &str, checked| Action::SetOverlay {
+            name: name.to_string(),
+            checked,
+        };
+
+        for (name, expected) in [
+            ("fl195", Overlay::FL195),
+            ("fl105", Overlay::FL105),
+            ("atzdz", Overlay::AtzDz),
+            ("custom:FL155", Overlay::Custom(155)),
+        ] {
+            let state = reduce(State::default(), overlay(name, true));
+            assert_eq!(state.settings.overlay, HashSet::from([expected]));
+
+            let state = reduce(state, overlay(name, false));
+            assert!(state.settings.overlay.is_empty());
+        }
+
+        // Unknown overlays don't drop the selection
+        let state = reduce(State::default(), overlay("fl195", true));
+        let next = reduce(state.clone(), overlay("fl999", true));
+        assert_eq!(next.settings, state.settings);
+        let next = reduce(state.clone(), set("overlay", "fl999"));
+        assert_eq!(next.settings, state.settings);
+
+        // String path clears with "no"
+        let state = reduce(state, set("overlay", "no"));
+        assert!(state.settings.overlay.is_empty());
+    }
+
+    #[test]
+    fn set_format() {
+        for format in [
+            Format::OpenAir,
+            Format::RatOnly,
+            Format::Competition,
+            Format::Tnp,
+            Format::GeoJson,
+            Format::Gpx,
+            Format::Cup,
+            Format::Kml,
+            Format::Csv,
+            Format::OpenAirLk,
+        ] {
+            let state = reduce(State::default(), Action::SetFormat(format));
+            assert_eq!(state.settings.format, format);
+
+            // String path gives the same format
+            let next = reduce(State::default(), set("format", format_value(format)));
+            assert_eq!(next.settings.format, format);
+        }
+
+        // Unknown string still falls back to OpenAir
+        let state = reduce(State::default(), Action::SetFormat(Format::Kml));
+        let state = reduce(state, set("format", "shapefile"));
+        assert_eq!(state.settings.format, Format::OpenAir);
+    }
+
+    #[test]
+    fn clear_all() {
+        let state = reduce(
+            State::default(),
+            Action::ReplaceLoa {
+                names: HashSet::from(["CAMBRIDGE RAZ".to_string()]),
+            },
+        );
+        let state = reduce(
+            state,
+            Action::ReplaceRat {
+                names: vec!["ROYAL WEDDING".to_string()],
+            },
+        );
+        let state = reduce(
+            state,
+            Action::ReplaceWave {
+                names: HashSet::from(["EDEN WAVE".to_string()]),
+            },
+        );
+        assert_eq!(state.settings.loa.len(), 1);
+        assert_eq!(state.settings.rat.len(), 1);
+        assert_eq!(state.settings.wave.len(), 1);
+
+        // Single undo entry for the whole clear
+        let undo = state.undo.len();
+        let state = reduce(state, Action::ClearAll);
+        assert!(state.settings.loa.is_empty());
+        assert!(state.settings.rat.is_empty());
+        assert!(state.settings.wave.is_empty());
+        assert_eq!(state.undo.len(), undo + 1);
+    }
+
+    #[test]
+    fn max_level_presets() {
+        for (preset, level) in [
+            (MaxLevelPreset::Everything, 660),
+            (MaxLevelPreset::Glider, 195),
+            (MaxLevelPreset::Fl125, 125),
+            (MaxLevelPreset::Low, 105),
+            (MaxLevelPreset::Fl65, 65),
+        ] {
+            let state = reduce(State::default(), Action::SetMaxLevelPreset(preset));
+            assert_eq!(state.settings.max_level, level);
+        }
+        assert_eq!(MaxLevelPreset::ALL.len(), 5);
+    }
+
+    #[test]
+    fn optional_type_off() {
+        let field = |settings: &Settings, name| match name {
+            "ils" => settings.ils,
+            "unlicensed" => settings.unlicensed,
+            "microlight" => settings.microlight,
+            "gliding" => settings.gliding,
+            "hirta_gvs" => settings.hirta_gvs,
+            _ => settings.obstacle,
+        };
+
+        for name in [
+            "ils",
+            "unlicensed",
+            "microlight",
+            "gliding",
+            "hirta_gvs",
+            "obstacle",
+        ] {
+            let state = reduce(State::default(), set(name, "classd"));
+            assert_eq!(
+                field(&state.settings, name),
+                Some(AirType::ClassD),
+                "{}",
+                name
+            );
+
+            // Unknown value leaves the field unchanged
+            let state = reduce(state, set(name, "classz"));
+            assert_eq!(
+                field(&state.settings, name),
+                Some(AirType::ClassD),
+                "{}",
+                name
+            );
+
+            for off in ["off", "none"] {
+                let next = reduce(state.clone(), set(name, off));
+                assert_eq!(field(&next.settings, name), None, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn airway_serde() {
+        let settings = Settings {
+            ils: Some(AirType::Awy),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&settings).unwrap();
+        assert!(json.contains("\"ils\":\"Awy\""));
+        assert_eq!(serde_json::from_str::<Settings>(&json).unwrap(), settings);
+
+        let query = settings.to_query_string();
+        assert_eq!(query, "ils=awy");
+        assert_eq!(Settings::from_query_string(&query), settings);
+
+        // Settings saved before the airway type still load
+        let mut old = serde_json::to_value(Settings::default()).unwrap();
+        old["ils"] = json!("ClassD");
+        let old: Settings = serde_json::from_value(old).unwrap();
+        assert_eq!(old.ils, Some(AirType::ClassD));
+    }
+
+    #[test]
+    fn custom_overlay() {
+        assert_eq!(parse_overlay("custom:120"), Some(Overlay::Custom(120)));
+        assert_eq!(parse_overlay("custom:FL155"), Some(Overlay::Custom(155)));
+        assert_eq!(parse_overlay("fl105"), Some(Overlay::FL105));
+
+        for value in [
+            "custom:",
+            "custom:abc",
+            "custom:0",
+            "custom:700",
+            "120",
+            "custom120",
+        ] {
+            assert_eq!(parse_overlay(value), None, "{}", value);
+        }
+
+        // Custom level survives a shared link
+        let state = reduce(State::default(), set("overlay", "custom:120"));
+        let query = state.settings.to_query_string();
+        assert_eq!(Settings::from_query_string(&query), state.settings);
     }
 }
@@ -0,0 +1,133 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+use crate::state::{migrate, Profile, Settings, SELECTION_RENAMES};
+use gloo::storage::{LocalStorage, Storage};
+use serde_json::Value;
+
+// Local storage keys. Bump the version if Settings changes incompatibly
+const SETTINGS_KEY: &str = "asselect.settings.v1";
+const LEGACY_SETTINGS_KEY: &str = "settings";
+const PROFILES_KEY: &str = "asselect.profiles.v1";
+
+// Stored JSON string, None if missing
+fn get_json(key: &str) -> Option<String> {
+    LocalStorage::raw().get_item(key).ok().flatten()
+}
+
+// Settings from stored JSON, falling back to the legacy key then default
+// if missing or unreadable
+fn decode_settings(current: Option<&str>, legacy: Option<&str>) -> Settings {
+    let parse = |json: Option<&str>| json.and_then(|json| serde_json::from_str::<Value>(json).ok());
+
+    parse(current)
+        .or_else(|| parse(legacy))
+        .map(|value| migrate(value, SELECTION_RENAMES))
+        .unwrap_or_default()
+}
+
+// Profiles from stored JSON, each migrated as for settings. Empty if
+// missing or unreadable
+fn decode_profiles(json: Option<&str>) -> Vec<Profile> {
+    let Some(mut value) = json.and_then(|json| serde_json::from_str::<Value>(json).ok()) else {
+        return Vec::new();
+    };
+
+    if let Value::Array(profiles) = &mut value {
+        for profile in profiles {
+            if let Some(settings) = profile.get_mut("settings") {
+                *settings = serde_json::to_value(migrate(settings.take(), SELECTION_RENAMES))
+                    .unwrap_or_default();
+            }
+        }
+    }
+    serde_json::from_value(value).unwrap_or_default()
+}
+
+// Restore settings, falling back to default if missing or unreadable
+pub fn load_settings() -> Settings {
+    decode_settings(
+        get_json(SETTINGS_KEY).as_deref(),
+        get_json(LEGACY_SETTINGS_KEY).as_deref(),
+    )
+}
+
+// Save settings
+pub fn save_settings(settings: &Settings) {
+    let _ = LocalStorage::set(SETTINGS_KEY, settings);
+}
+
+// Restore saved profiles
+pub fn load_profiles() -> Vec<Profile> {
+    decode_profiles(get_json(PROFILES_KEY).as_deref())
+}
+
+// Save profiles
+pub fn save_profiles(profiles: &[Profile]) {
+    let _ = LocalStorage::set(PROFILES_KEY, profiles);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        let mut settings = Settings {
+            max_level: 125,
+            radio: true,
+            ..Settings::default()
+        };
+        settings.loa.insert("CAMBRIDGE RAZ".to_string());
+        settings
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let json = serde_json::to_string(&settings()).unwrap();
+        assert_eq!(decode_settings(Some(&json), None), settings());
+        assert_eq!(decode_settings(None, None), Settings::default());
+    }
+
+    #[test]
+    fn legacy_settings() {
+        let json = serde_json::to_string(&settings()).unwrap();
+        let other = serde_json::to_string(&Settings::default()).unwrap();
+
+        // Legacy key only used if the current key is missing or corrupt
+        assert_eq!(decode_settings(None, Some(&json)), settings());
+        assert_eq!(decode_settings(Some("{corrupt"), Some(&json)), settings());
+        assert_eq!(
+            decode_settings(Some(&other), Some(&json)),
+            Settings::default()
+        );
+        assert_eq!(
+            decode_settings(Some("{corrupt"), Some("[1,")),
+            Settings::default()
+        );
+    }
+
+    #[test]
+    fn profiles_round_trip() {
+        let profiles = vec![Profile {
+            name: "Club".to_string(),
+            settings: settings(),
+        }];
+        let json = serde_json::to_string(&profiles).unwrap();
+
+        assert_eq!(decode_profiles(Some(&json)), profiles);
+        assert_eq!(decode_profiles(None), Vec::new());
+        assert_eq!(decode_profiles(Some("[{\"name\":")), Vec::new());
+    }
+}
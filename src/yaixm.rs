@@ -79,6 +79,8 @@ pub enum LocalType {
 
 #[derive(Clone, Copy, Deserialize, Debug, Eq, Hash, PartialEq)]
 pub enum Rule {
+    #[serde(rename = "INACTIVE")]
+    Inactive,
     #[serde(rename = "INTENSE")]
     Intense,
     #[serde(rename = "LOA")]
@@ -134,6 +136,7 @@ pub struct Volume {
     pub rules: Option<Vec<Rule>>,
     pub seq: Option<String>,
     pub frequency: Option<f64>,
+    pub callsign: Option<String>,
     pub boundary: Vec<Boundary>,
 }
 
@@ -149,6 +152,9 @@ pub struct Feature {
     pub icao_class: Option<IcaoClass>,
     pub rules: Option<Vec<Rule>>,
     pub geometry: Vec<Volume>,
+    pub date: Option<String>,
+    pub hours: Option<String>,
+    pub notes: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Debug)]
@@ -159,6 +165,7 @@ pub struct Replace {
 
 #[derive(Deserialize, Debug)]
 pub struct LoaArea {
+    #[allow(dead_code)]
     pub name: String,
     pub add: Vec<Feature>,
     pub replace: Option<Vec<Replace>>,
@@ -188,7 +195,9 @@ pub struct Service {
 #[derive(Deserialize, Debug)]
 pub struct Release {
     pub airac_date: String,
+    #[allow(dead_code)]
     pub timestamp: String,
+    #[allow(dead_code)]
     pub schema_version: u8,
     pub note: String,
     pub commit: String,
@@ -229,6 +238,41 @@ pub fn loa_names(yaixm: &Yaixm) -> Vec<String> {
         .collect::<Vec<String>>()
 }
 
+// Non-default LOAs grouped by purpose, gliding (any area adding gliding
+// airspace) or transit, each with sorted member names
+pub fn loa_categories(yaixm: &Yaixm) -> Vec<(String, Vec<String>)> {
+    let mut categories: Vec<(String, Vec<String>)> = Vec::new();
+    for loa in yaixm.loa.iter().filter(|x| !x.default.unwrap_or(false)) {
+        let gliding = loa
+            .areas
+            .iter()
+            .flat_map(|area| area.add.iter())
+            .any(|feature| feature.local_type == Some(LocalType::Glider));
+        let category = if gliding { "Gliding" } else { "Transit" };
+
+        match categories.iter_mut().find(|(name, _)| name == category) {
+            Some((_, members)) => members.push(loa.name.clone()),
+            None => categories.push((category.to_string(), vec![loa.name.clone()])),
+        }
+    }
+
+    categories.sort();
+    for (_, members) in categories.iter_mut() {
+        members.sort();
+    }
+    categories
+}
+
+// List of danger areas
+pub fn danger_names(yaixm: &Yaixm) -> Vec<String> {
+    yaixm
+        .airspace
+        .iter()
+        .filter(|x| x.icao_type == IcaoType::D)
+        .map(|x| x.name.clone())
+        .collect::<Vec<String>>()
+}
+
 // List of Wave boxes
 pub fn wave_names(yaixm: &Yaixm) -> Vec<String> {
     yaixm
@@ -238,3 +282,17 @@ pub fn wave_names(yaixm: &Yaixm) -> Vec<String> {
         .map(|x| x.name.clone())
         .collect::<Vec<String>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::tests::fixture;
+
+    #[test]
+    fn loa_category() {
+        assert_eq!(
+            loa_categories(&fixture()),
+            [("Gliding".to_string(), vec!["CAMBRIDGE RAZ".to_string()])]
+        );
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright 2023, Alan Sparrow
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or (at
+// your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//
+// Minimal ZIP archive writer, files are stored without compression
+
+// MS-DOS date for 1980-01-01, the earliest a ZIP file can record
+const DOS_DATE: u16 = 0x21;
+
+// General purpose flag for UTF-8 filenames
+const UTF8_FLAG: u16 = 0x0800;
+
+// CRC-32 (IEEE) checksum
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn push_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+// ZIP archive from filename/content pairs
+pub fn zip(entries: &[(String, String)]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut directory = Vec::new();
+
+    for (name, content) in entries {
+        let offset = output.len() as u32;
+        let crc = crc32(content.as_bytes());
+        let size = content.len() as u32;
+
+        // Local file header
+        push_u32(&mut output, 0x04034b50);
+        push_u16(&mut output, 20);
+        push_u16(&mut output, UTF8_FLAG);
+        push_u16(&mut output, 0);
+        push_u16(&mut output, 0);
+        push_u16(&mut output, DOS_DATE);
+        push_u32(&mut output, crc);
+        push_u32(&mut output, size);
+        push_u32(&mut output, size);
+        push_u16(&mut output, name.len() as u16);
+        push_u16(&mut output, 0);
+        output.extend_from_slice(name.as_bytes());
+        output.extend_from_slice(content.as_bytes());
+
+        // Central directory entry
+        push_u32(&mut directory, 0x02014b50);
+        push_u16(&mut directory, 20);
+        push_u16(&mut directory, 20);
+        push_u16(&mut directory, UTF8_FLAG);
+        push_u16(&mut directory, 0);
+        push_u16(&mut directory, 0);
+        push_u16(&mut directory, DOS_DATE);
+        push_u32(&mut directory, crc);
+        push_u32(&mut directory, size);
+        push_u32(&mut directory, size);
+        push_u16(&mut directory, name.len() as u16);
+        push_u16(&mut directory, 0);
+        push_u16(&mut directory, 0);
+        push_u16(&mut directory, 0);
+        push_u16(&mut directory, 0);
+        push_u32(&mut directory, 0);
+        push_u32(&mut directory, offset);
+        directory.extend_from_slice(name.as_bytes());
+    }
+
+    // End of central directory record
+    let directory_offset = output.len() as u32;
+    let directory_size = directory.len() as u32;
+    output.append(&mut directory);
+
+    push_u32(&mut output, 0x06054b50);
+    push_u16(&mut output, 0);
+    push_u16(&mut output, 0);
+    push_u16(&mut output, entries.len() as u16);
+    push_u16(&mut output, entries.len() as u16);
+    push_u32(&mut output, directory_size);
+    push_u32(&mut output, directory_offset);
+    push_u16(&mut output, 0);
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn crc_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn headers() {
+        let entries = vec![
+            ("a.txt".to_string(), "123456789".to_string()),
+            ("b.csv".to_string(), "x,y\n".to_string()),
+        ];
+        let data = zip(&entries);
+
+        // First local file header, followed by name and stored content
+        assert_eq!(u32_at(&data, 0), 0x04034b50);
+        assert_eq!(u16_at(&data, 6), UTF8_FLAG);
+        assert_eq!(u32_at(&data, 14), 0xcbf43926);
+        assert_eq!(u32_at(&data, 18), 9);
+        assert_eq!(u32_at(&data, 22), 9);
+        assert_eq!(u16_at(&data, 26), 5);
+        assert_eq!(&data[30..35], b"a.txt");
+        assert_eq!(&data[35..44], b"123456789");
+
+        // Second local file header
+        assert_eq!(u32_at(&data, 44), 0x04034b50);
+        assert_eq!(u32_at(&data, 58), crc32(b"x,y\n"));
+
+        // End of central directory record, pointing at the directory
+        let end = data.len() - 22;
+        assert_eq!(u32_at(&data, end), 0x06054b50);
+        assert_eq!(u16_at(&data, end + 8), 2);
+        assert_eq!(u16_at(&data, end + 10), 2);
+        let directory = u32_at(&data, end + 16) as usize;
+        assert_eq!(directory + u32_at(&data, end + 12) as usize, end);
+        assert_eq!(u32_at(&data, directory), 0x02014b50);
+        assert_eq!(u32_at(&data, directory + 16), 0xcbf43926);
+        assert_eq!(u32_at(&data, directory + 42), 0);
+    }
+}